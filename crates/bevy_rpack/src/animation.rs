@@ -0,0 +1,333 @@
+use crate::AnimationLoopMode;
+use crate::plugin::RpackAtlasAsset;
+use bevy_app::{App, Plugin, Update};
+use bevy_asset::{AssetId, Assets, Handle};
+use bevy_ecs::prelude::*;
+use bevy_reflect::Reflect;
+use bevy_sprite::{Anchor, Sprite, SpriteImageMode};
+use bevy_time::Time;
+use bevy_ui::widget::{ImageNode, NodeImageMode};
+
+/// Plugin playing [`RpackAnimation`] components on [`Sprite`] and [`ImageNode`] entities.
+///
+/// Requires [`RpackAssetPlugin`](crate::prelude::RpackAssetPlugin) to be added as well.
+pub struct RpackAnimationPlugin;
+
+impl Plugin for RpackAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<RpackAnimation>();
+        app.add_message::<RpackAnimationFinished>();
+        app.add_systems(Update, advance_rpack_animations);
+    }
+}
+
+/// Plays an animation from any of the loaded atlases on the [`Sprite`] or [`ImageNode`] of the entity.
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component, Debug)]
+pub struct RpackAnimation {
+    /// Name of the animation in the atlas.
+    pub name: String,
+    /// Playback speed multiplier, `1.0` uses the frame durations from the atlas.
+    pub speed: f32,
+    /// Overrides the loop mode defined in the atlas.
+    pub loop_mode: Option<AnimationLoopMode>,
+    /// Stops advancing frames while `true`.
+    pub paused: bool,
+    /// Atlas containing the animation, found among the loaded atlases when not set.
+    atlas: Option<AssetId<RpackAtlasAsset>>,
+    playback: Playback,
+}
+
+/// Position of the playback within an animation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Reflect)]
+struct Playback {
+    /// Index of the current frame.
+    frame: usize,
+    /// Seconds spent on the current frame.
+    elapsed: f32,
+    /// Frames are played backwards, used by [`AnimationLoopMode::PingPong`].
+    reversed: bool,
+    /// Last frame of an [`AnimationLoopMode::Once`] animation was reached.
+    finished: bool,
+}
+
+/// Sent when an [`RpackAnimation`] played with [`AnimationLoopMode::Once`] reaches its last frame.
+#[derive(Message, Debug, Clone)]
+pub struct RpackAnimationFinished {
+    /// Entity playing the animation.
+    pub entity: Entity,
+    /// Name of the finished animation.
+    pub name: String,
+}
+
+impl RpackAnimation {
+    /// Creates a component playing the animation with given name from its first frame.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            speed: 1.0,
+            loop_mode: None,
+            paused: false,
+            atlas: None,
+            playback: Playback::default(),
+        }
+    }
+
+    /// Sets the playback speed multiplier.
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Plays the animation from the given atlas instead of searching the loaded atlases for it.
+    pub fn with_atlas(mut self, atlas: &Handle<RpackAtlasAsset>) -> Self {
+        self.atlas = Some(atlas.id());
+        self
+    }
+
+    /// Overrides the loop mode defined in the atlas.
+    pub fn with_loop_mode(mut self, loop_mode: AnimationLoopMode) -> Self {
+        self.loop_mode = Some(loop_mode);
+        self
+    }
+
+    /// Index of the current frame within the animation.
+    pub fn frame(&self) -> usize {
+        self.playback.frame
+    }
+
+    /// Returns true once an animation played with [`AnimationLoopMode::Once`] reached its last frame.
+    pub fn is_finished(&self) -> bool {
+        self.playback.finished
+    }
+
+    /// Plays the animation again from its first frame.
+    pub fn restart(&mut self) {
+        self.playback = Playback::default();
+    }
+}
+
+/// Returns the playback moved to the next frame out of `frame_count` frames.
+fn step(playback: Playback, frame_count: usize, loop_mode: AnimationLoopMode) -> Playback {
+    let last = frame_count.saturating_sub(1);
+    let Playback {
+        mut frame,
+        mut reversed,
+        mut finished,
+        ..
+    } = playback;
+    match loop_mode {
+        AnimationLoopMode::Loop => frame = (frame + 1) % frame_count.max(1),
+        AnimationLoopMode::Once if frame >= last => {
+            frame = last;
+            finished = true;
+        }
+        AnimationLoopMode::Once => frame += 1,
+        AnimationLoopMode::PingPong if last == 0 => frame = 0,
+        AnimationLoopMode::PingPong => {
+            if reversed && frame == 0 || !reversed && frame >= last {
+                reversed = !reversed;
+            }
+            frame = if reversed {
+                frame.min(last) - 1
+            } else {
+                frame + 1
+            };
+        }
+    }
+    Playback {
+        frame,
+        reversed,
+        finished,
+        ..playback
+    }
+}
+
+/// Returns the playback advanced by `delta` seconds, `duration` gives the length of a frame in seconds.
+///
+/// The returned flag is true only when the animation finished during this call.
+fn advance(
+    mut playback: Playback,
+    delta: f32,
+    frame_count: usize,
+    loop_mode: AnimationLoopMode,
+    duration: impl Fn(usize) -> f32,
+) -> (Playback, bool) {
+    if playback.finished || frame_count == 0 {
+        return (playback, false);
+    }
+    playback.elapsed += delta;
+    loop {
+        let frame_duration = duration(playback.frame);
+        if playback.elapsed < frame_duration {
+            return (playback, false);
+        }
+        playback.elapsed -= frame_duration;
+        playback = step(playback, frame_count, loop_mode);
+        if playback.finished {
+            playback.elapsed = 0.0;
+            return (playback, true);
+        }
+    }
+}
+
+/// Components updated by [`advance_rpack_animations`].
+type AnimatedEntity<'a> = (
+    Entity,
+    &'a mut RpackAnimation,
+    Option<&'a mut Sprite>,
+    Option<&'a mut ImageNode>,
+    Option<&'a mut Anchor>,
+);
+
+/// Advances [`RpackAnimation`] frames and updates atlas, image and anchor of the entity.
+pub fn advance_rpack_animations(
+    time: Res<Time>,
+    atlases: Res<Assets<RpackAtlasAsset>>,
+    mut query: Query<AnimatedEntity>,
+    mut finished: MessageWriter<RpackAnimationFinished>,
+) {
+    for (entity, mut animation, sprite, image_node, anchor) in query.iter_mut() {
+        // Loaded atlases are searched once, the atlas containing the animation is remembered
+        let cached = animation
+            .atlas
+            .and_then(|id| atlases.get(id))
+            .and_then(|a| a.get_animation(&animation.name).map(|data| (a, data)));
+        let Some((atlas, data)) = cached.or_else(|| {
+            let (id, atlas, data) = atlases
+                .iter()
+                .find_map(|(id, a)| a.get_animation(&animation.name).map(|data| (id, a, data)))?;
+            animation.atlas = Some(id);
+            Some((atlas, data))
+        }) else {
+            continue;
+        };
+        if data.frames.is_empty() {
+            continue;
+        }
+        let loop_mode = animation.loop_mode.unwrap_or(data.loop_mode);
+        // Frames without duration would never advance the time
+        if !animation.paused && data.total_duration() > 0 {
+            let delta = time.delta_secs() * animation.speed.max(0.0);
+            let (playback, just_finished) = advance(
+                animation.playback,
+                delta,
+                data.frames.len(),
+                loop_mode,
+                |i| data.duration(i) as f32 / 1000.0,
+            );
+            animation.playback = playback;
+            if just_finished {
+                finished.write(RpackAnimationFinished {
+                    entity,
+                    name: animation.name.clone(),
+                });
+            }
+        }
+
+        let key = &data.frames[animation.playback.frame.min(data.frames.len() - 1)];
+        let Ok((texture_atlas, image)) = atlas.get_atlas_data(key) else {
+            continue;
+        };
+        let slicer = atlas.get_slicer(key);
+        if let Some(mut sprite) = sprite
+            && (sprite.texture_atlas.as_ref() != Some(&texture_atlas) || sprite.image != image)
+        {
+            sprite.texture_atlas = Some(texture_atlas.clone());
+            sprite.image = image.clone();
+            match slicer.clone() {
+                Some(slicer) => sprite.image_mode = SpriteImageMode::Sliced(slicer),
+                None if matches!(sprite.image_mode, SpriteImageMode::Sliced(_)) => {
+                    sprite.image_mode = SpriteImageMode::Auto;
+                }
+                None => {}
+            }
+        }
+        if let Some(mut image_node) = image_node
+            && (image_node.texture_atlas.as_ref() != Some(&texture_atlas)
+                || image_node.image != image)
+        {
+            image_node.texture_atlas = Some(texture_atlas);
+            image_node.image = image;
+            match slicer {
+                Some(slicer) => image_node.image_mode = NodeImageMode::Sliced(slicer),
+                None if matches!(image_node.image_mode, NodeImageMode::Sliced(_)) => {
+                    image_node.image_mode = NodeImageMode::Auto;
+                }
+                None => {}
+            }
+        }
+        if let (Some(mut anchor), Ok(frame_anchor)) = (anchor, atlas.get_anchor(key)) {
+            anchor.set_if_neq(frame_anchor);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(
+        playback: Playback,
+        count: usize,
+        loop_mode: AnimationLoopMode,
+        steps: usize,
+    ) -> Vec<usize> {
+        std::iter::successors(Some(playback), |p| Some(step(*p, count, loop_mode)))
+            .take(steps)
+            .map(|p| p.frame)
+            .collect()
+    }
+
+    #[test]
+    fn ping_pong_reverses_at_both_ends() {
+        let played = frames(Playback::default(), 3, AnimationLoopMode::PingPong, 9);
+        assert_eq!(played, [0, 1, 2, 1, 0, 1, 2, 1, 0]);
+        let single = frames(Playback::default(), 1, AnimationLoopMode::PingPong, 3);
+        assert_eq!(single, [0, 0, 0]);
+    }
+
+    #[test]
+    fn once_clamps_to_the_last_frame() {
+        let played = frames(Playback::default(), 3, AnimationLoopMode::Once, 6);
+        assert_eq!(played, [0, 1, 2, 2, 2, 2]);
+        let playback = step(
+            Playback {
+                frame: 7,
+                ..Default::default()
+            },
+            3,
+            AnimationLoopMode::Once,
+        );
+        assert_eq!(playback.frame, 2);
+        assert!(playback.finished);
+    }
+
+    #[test]
+    fn once_reports_finishing_exactly_one_time() {
+        let mut playback = Playback::default();
+        let mut reports = 0;
+        // Large steps skip several frames at once, later calls keep the finished state
+        for _ in 0..10 {
+            let (next, just_finished) =
+                advance(playback, 0.25, 4, AnimationLoopMode::Once, |_| 0.1);
+            playback = next;
+            reports += usize::from(just_finished);
+        }
+        assert_eq!(reports, 1);
+        assert!(playback.finished);
+        assert_eq!(playback.frame, 3);
+    }
+
+    #[test]
+    fn loop_never_finishes() {
+        let mut playback = Playback::default();
+        for _ in 0..10 {
+            let (next, just_finished) =
+                advance(playback, 0.25, 4, AnimationLoopMode::Loop, |_| 0.125);
+            assert!(!just_finished);
+            playback = next;
+        }
+        assert_eq!(playback.frame, 20 % 4);
+    }
+}
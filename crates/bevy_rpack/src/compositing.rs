@@ -0,0 +1,122 @@
+//! Drawing images into atlas pages and extracting them back.
+
+use std::{fmt::Display, str::FromStr};
+
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+/// Defines how the extrusion area around each frame is filled.
+#[derive(Clone, Debug, Default, Copy, Serialize, Deserialize, PartialEq)]
+pub enum ExtrusionFill {
+    /// Leaves the extrusion area transparent.
+    Transparent,
+    /// Repeats the outermost pixels of the frame.
+    #[default]
+    EdgeRepeat,
+    /// Fills the extrusion area with a solid RGBA color.
+    Color([u8; 4]),
+}
+
+impl FromStr for ExtrusionFill {
+    type Err = String;
+
+    /// Parses `transparent`, `edge-repeat` or a `#rrggbb`/`#rrggbbaa` color.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "transparent" => Ok(ExtrusionFill::Transparent),
+            "edge-repeat" => Ok(ExtrusionFill::EdgeRepeat),
+            _ => {
+                let hex = s.strip_prefix('#').unwrap_or(s);
+                if (hex.len() != 6 && hex.len() != 8) || !hex.is_ascii() {
+                    return Err(format!(
+                        "expected `transparent`, `edge-repeat` or `#rrggbb[aa]` color, got `{s}`"
+                    ));
+                }
+                let mut color = [255; 4];
+                for (i, channel) in color.iter_mut().enumerate().take(hex.len() / 2) {
+                    *channel = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+                        .map_err(|e| format!("invalid color `{s}`: {e}"))?;
+                }
+                Ok(ExtrusionFill::Color(color))
+            }
+        }
+    }
+}
+
+impl Display for ExtrusionFill {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtrusionFill::Transparent => f.write_str("transparent"),
+            ExtrusionFill::EdgeRepeat => f.write_str("edge-repeat"),
+            ExtrusionFill::Color([r, g, b, a]) => write!(f, "#{r:02x}{g:02x}{b:02x}{a:02x}"),
+        }
+    }
+}
+
+/// Draws `image` into `page` with its top-left corner at (`x`, `y`) and fills `extrusion` pixels around it.
+///
+/// `rotation` is `None` for images drawn as they are, otherwise images are rotated by 90 degrees,
+/// counter-clockwise when it is `Some(true)` and clockwise when it is `Some(false)`.
+pub fn draw_frame(
+    page: &mut RgbaImage,
+    image: &DynamicImage,
+    (x, y): (u32, u32),
+    rotation: Option<bool>,
+    extrusion: u32,
+    fill: ExtrusionFill,
+) {
+    let image = match rotation {
+        Some(true) => image.rotate270(),
+        Some(false) => image.rotate90(),
+        None => image.clone(),
+    };
+    let (w, h) = image.dimensions();
+    image::imageops::replace(page, &image.to_rgba8(), x as i64, y as i64);
+    if extrusion == 0 || w == 0 || h == 0 {
+        return;
+    }
+
+    let left = x.saturating_sub(extrusion);
+    let top = y.saturating_sub(extrusion);
+    let right = (x + w + extrusion).min(page.width());
+    let bottom = (y + h + extrusion).min(page.height());
+    for py in top..bottom {
+        for px in left..right {
+            let inside_x = (x..x + w).contains(&px);
+            let inside_y = (y..y + h).contains(&py);
+            if inside_x && inside_y {
+                continue;
+            }
+            let pixel = match fill {
+                ExtrusionFill::Transparent => Rgba([0, 0, 0, 0]),
+                ExtrusionFill::Color(color) => Rgba(color),
+                ExtrusionFill::EdgeRepeat => {
+                    *page.get_pixel(px.clamp(x, x + w - 1), py.clamp(y, y + h - 1))
+                }
+            };
+            page.put_pixel(px, py, pixel);
+        }
+    }
+}
+
+/// Crops `frame` from its `page` and restores the source image, undoing rotation and trimming.
+pub fn extract_frame(page: &DynamicImage, frame: &crate::AtlasFrame) -> DynamicImage {
+    let rect = frame.frame;
+    let cropped = page.crop_imm(rect.x, rect.y, rect.w, rect.h);
+    let cropped = match (frame.rotated, frame.counter_clockwise) {
+        (true, true) => cropped.rotate90(),
+        (true, false) => cropped.rotate270(),
+        (false, _) => cropped,
+    };
+    let Some(trim) = frame.trim else {
+        return cropped;
+    };
+    let mut source = RgbaImage::new(trim.source_size[0], trim.source_size[1]);
+    image::imageops::replace(
+        &mut source,
+        &cropped.to_rgba8(),
+        trim.offset[0] as i64,
+        trim.offset[1] as i64,
+    );
+    DynamicImage::from(source)
+}
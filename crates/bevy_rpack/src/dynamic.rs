@@ -0,0 +1,332 @@
+use crate::packer::SkylinePacker;
+use crate::plugin::{RpackAtlasAsset, RpackAtlasPage};
+use crate::{AtlasFrame, SerializableRect};
+use bevy_asset::{AssetServer, Assets, Handle, RenderAssetUsages};
+use bevy_derive::Deref;
+use bevy_ecs::resource::Resource;
+use bevy_ecs::system::{Res, ResMut};
+use bevy_image::{Image, ImageSampler, TextureAtlasLayout};
+use bevy_math::UVec2;
+use bevy_platform::collections::HashMap;
+use texture_packer::{Rect, TexturePackerConfig};
+use thiserror::Error;
+
+/// Errors that can occur while adding images to a [`DynamicRpackAtlas`].
+#[derive(Debug, Error)]
+pub enum DynamicRpackAtlasError {
+    /// The image is larger than a page of the atlas.
+    #[error("image {0} does not fit into an empty page")]
+    TooLarge(String),
+    /// The image could not be converted to RGBA.
+    #[error("image {0} has an unsupported format: {1}")]
+    UnsupportedFormat(String, String),
+    /// The image added by handle failed to load.
+    #[error("image {0} failed to load")]
+    LoadFailed(String),
+    /// The page image of the atlas was removed from the assets.
+    #[error("page image of the atlas is missing")]
+    MissingPage,
+}
+
+/// Atlas packed at runtime from images added one by one, for example from mods or user-generated content.
+///
+/// Images are packed into pages of a fixed size, a new page is created when an image does not fit into the last one.
+/// Adding images never changes the pages and layout indices of frames added before.
+///
+/// Dereferences to [`RpackAtlasAsset`], so frames are used with the same key-based API,
+/// like [`RpackAtlasAsset::try_make_sprite`] and [`RpackAtlasAsset::try_make_image_node`].
+///
+/// # Example
+/// ```rust,ignore
+/// fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+///     let mut atlas = DynamicRpackAtlas::new(1024);
+///     atlas.add_image_handle("mods/hat", asset_server.load("mods/hat.png"));
+///     commands.insert_resource(atlas);
+/// }
+///
+/// // Once the image is loaded and packed by the `RpackAssetPlugin`
+/// fn spawn(mut commands: Commands, atlas: Res<DynamicRpackAtlas>) {
+///     if let Ok(sprite) = atlas.try_make_sprite("mods/hat") {
+///         commands.spawn(sprite);
+///     }
+/// }
+/// ```
+#[derive(Resource, Deref)]
+pub struct DynamicRpackAtlas {
+    #[deref]
+    asset: RpackAtlasAsset,
+    page_size: u32,
+    padding: u32,
+    sampler: ImageSampler,
+    /// Packers of the pages, in the same order as `asset.pages`.
+    packers: Vec<SkylinePacker>,
+    /// Images added by handle that are not loaded yet.
+    pending: Vec<(String, Handle<Image>)>,
+    /// Errors of pending images that could not be packed.
+    errors: Vec<DynamicRpackAtlasError>,
+}
+
+impl DynamicRpackAtlas {
+    /// Creates an empty atlas with square pages of `page_size` pixels.
+    pub fn new(page_size: u32) -> Self {
+        Self {
+            asset: RpackAtlasAsset {
+                image: Handle::default(),
+                atlas: Handle::default(),
+                files: HashMap::new(),
+                frames: HashMap::new(),
+                pages: Vec::new(),
+                animations: HashMap::new(),
+            },
+            page_size,
+            padding: 2,
+            sampler: ImageSampler::Descriptor(bevy_image::ImageSamplerDescriptor::nearest()),
+            packers: Vec::new(),
+            pending: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Sets the padding between frames in pixels. Default value is `2`.
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Sets the sampler of the pages created from now on. Default value is `nearest`.
+    pub fn with_sampler(mut self, sampler: ImageSampler) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    /// Returns the atlas with all frames packed so far.
+    pub fn asset(&self) -> &RpackAtlasAsset {
+        &self.asset
+    }
+
+    /// Returns `true` if the atlas contains a frame with the given key.
+    pub fn contains<T: AsRef<str>>(&self, key: T) -> bool {
+        self.asset.files.contains_key(key.as_ref())
+    }
+
+    /// Returns `true` if there are images added by handle that are not loaded yet.
+    pub fn has_pending_images(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Packs the `image` as a frame with the given `key`.
+    ///
+    /// Adding an existing key packs the image again and points the key at the new frame.
+    pub fn add_image<T: Into<String>>(
+        &mut self,
+        key: T,
+        image: &Image,
+        images: &mut Assets<Image>,
+        layouts: &mut Assets<TextureAtlasLayout>,
+    ) -> Result<(), DynamicRpackAtlasError> {
+        let key = key.into();
+        let source = image
+            .clone()
+            .try_into_dynamic()
+            .map_err(|e| DynamicRpackAtlasError::UnsupportedFormat(key.clone(), e.to_string()))?
+            .to_rgba8();
+        let rect = Rect::new(0, 0, source.width(), source.height());
+        let placement = match self.packers.last_mut().and_then(|p| p.pack_rect(&rect)) {
+            Some(placement) => placement,
+            None => {
+                // Padding is added to every image, so the area is extended to not require it after the last one
+                let mut packer = SkylinePacker::new(TexturePackerConfig {
+                    max_width: self.page_size + self.padding,
+                    max_height: self.page_size + self.padding,
+                    allow_rotation: false,
+                    texture_padding: self.padding,
+                    texture_extrusion: 0,
+                    ..Default::default()
+                });
+                let Some(placement) = packer.pack_rect(&rect) else {
+                    return Err(DynamicRpackAtlasError::TooLarge(key));
+                };
+                self.add_page(packer, images, layouts);
+                placement
+            }
+        };
+
+        let page_index = self.packers.len() - 1;
+        let page = &self.asset.pages[page_index];
+        let mut page_image = images
+            .get_mut(&page.image)
+            .ok_or(DynamicRpackAtlasError::MissingPage)?;
+        let page_width = page_image.width() as usize;
+        let Some(data) = page_image.data.as_mut() else {
+            return Err(DynamicRpackAtlasError::MissingPage);
+        };
+        let row_len = source.width() as usize * 4;
+        for (y, row) in source.as_raw().chunks_exact(row_len).enumerate() {
+            let start = ((placement.y as usize + y) * page_width + placement.x as usize) * 4;
+            data[start..start + row_len].copy_from_slice(row);
+        }
+
+        let frame = SerializableRect {
+            x: placement.x,
+            y: placement.y,
+            w: placement.w,
+            h: placement.h,
+        };
+        let mut layout = layouts
+            .get_mut(&page.atlas)
+            .ok_or(DynamicRpackAtlasError::MissingPage)?;
+        let index = layout.add_texture(frame.into());
+        self.asset.files.insert(key.clone(), index);
+        self.asset.frames.insert(
+            key.clone(),
+            AtlasFrame {
+                key,
+                frame,
+                page: page_index,
+                trim: None,
+                rotated: false,
+                counter_clockwise: false,
+                slice: None,
+                pivot: None,
+            },
+        );
+        Ok(())
+    }
+
+    /// Adds the image behind `handle` once it is loaded.
+    ///
+    /// Pending images are packed by [`DynamicRpackAtlas::pack_pending_images`], which runs automatically
+    /// for the atlas inserted as a resource when the [`RpackAssetPlugin`](crate::prelude::RpackAssetPlugin) is added.
+    pub fn add_image_handle<T: Into<String>>(&mut self, key: T, handle: Handle<Image>) {
+        self.pending.push((key.into(), handle));
+    }
+
+    /// Packs images added by handle that finished loading.
+    ///
+    /// Errors of images that could not be packed are available from [`DynamicRpackAtlas::take_errors`].
+    pub fn pack_pending_images(
+        &mut self,
+        images: &mut Assets<Image>,
+        layouts: &mut Assets<TextureAtlasLayout>,
+    ) {
+        let pending = core::mem::take(&mut self.pending);
+        for (key, handle) in pending {
+            let Some(image) = images.get(&handle).cloned() else {
+                self.pending.push((key, handle));
+                continue;
+            };
+            if let Err(e) = self.add_image(key, &image, images, layouts) {
+                self.errors.push(e);
+            }
+        }
+    }
+
+    /// Returns and clears errors of images added by handle that failed to load or could not be packed.
+    pub fn take_errors(&mut self) -> Vec<DynamicRpackAtlasError> {
+        core::mem::take(&mut self.errors)
+    }
+
+    fn add_page(
+        &mut self,
+        packer: SkylinePacker,
+        images: &mut Assets<Image>,
+        layouts: &mut Assets<TextureAtlasLayout>,
+    ) {
+        let mut image = Image::from_dynamic(
+            image::RgbaImage::new(self.page_size, self.page_size).into(),
+            true,
+            RenderAssetUsages::default(),
+        );
+        image.sampler = self.sampler.clone();
+        let page = RpackAtlasPage {
+            image: images.add(image),
+            atlas: layouts.add(TextureAtlasLayout::new_empty(UVec2::splat(self.page_size))),
+        };
+        if self.packers.is_empty() {
+            self.asset.image = page.image.clone();
+            self.asset.atlas = page.atlas.clone();
+        }
+        self.packers.push(packer);
+        self.asset.pages.push(page);
+    }
+}
+
+/// Packs the pending images of the [`DynamicRpackAtlas`] resource once they are loaded.
+pub fn pack_dynamic_atlas_images(
+    atlas: Option<ResMut<DynamicRpackAtlas>>,
+    mut images: ResMut<Assets<Image>>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+    asset_server: Res<AssetServer>,
+) {
+    let Some(mut atlas) = atlas.filter(|atlas| atlas.has_pending_images()) else {
+        return;
+    };
+    let DynamicRpackAtlas {
+        pending, errors, ..
+    } = atlas.as_mut();
+    pending.retain(|(key, handle)| {
+        let failed = asset_server.load_state(handle).is_failed();
+        if failed {
+            errors.push(DynamicRpackAtlasError::LoadFailed(key.clone()));
+        }
+        !failed
+    });
+    atlas.pack_pending_images(&mut images, &mut layouts);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(w: u32, h: u32) -> Image {
+        Image::from_dynamic(
+            image::RgbaImage::new(w, h).into(),
+            true,
+            RenderAssetUsages::default(),
+        )
+    }
+
+    #[test]
+    fn add_image_fills_pages_to_their_edge() {
+        let mut images = Assets::<Image>::default();
+        let mut layouts = Assets::<TextureAtlasLayout>::default();
+        let mut atlas = DynamicRpackAtlas::new(64);
+
+        atlas
+            .add_image("edge", &image(62, 62), &mut images, &mut layouts)
+            .unwrap();
+        atlas
+            .add_image("full", &image(64, 64), &mut images, &mut layouts)
+            .unwrap();
+        assert_eq!(
+            atlas.frames["edge"].frame,
+            SerializableRect {
+                x: 0,
+                y: 0,
+                w: 62,
+                h: 62
+            }
+        );
+        assert_eq!(
+            atlas.frames["full"].frame,
+            SerializableRect {
+                x: 0,
+                y: 0,
+                w: 64,
+                h: 64
+            }
+        );
+        assert_eq!(atlas.frames["full"].page, 1);
+
+        atlas
+            .add_image("next", &image(1, 1), &mut images, &mut layouts)
+            .unwrap();
+        assert_eq!(atlas.frames["next"].page, 2);
+        assert!(matches!(
+            atlas.add_image("large", &image(65, 64), &mut images, &mut layouts),
+            Err(DynamicRpackAtlasError::TooLarge(key)) if key == "large"
+        ));
+        assert_eq!(atlas.pages.len(), 3);
+        assert!(!atlas.contains("large"));
+    }
+}
@@ -0,0 +1,295 @@
+//! Options of `.rpack_gen.json` configs and helpers shared with `rpack_cli`.
+//!
+//! The `RpackAtlasGenConfigLoader` and the asset processor read the packing options with [`AtlasGenConfig`]
+//! and build atlases with [`Spritesheet::build`](crate::spritesheet::Spritesheet::build) like `rpack_cli`.
+
+use alloc::collections::BTreeMap;
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use texture_packer::TexturePackerConfig;
+use thiserror::Error;
+
+use crate::compositing::ExtrusionFill;
+use crate::packer::{AutoSizeConfig, PackingAlgorithm, SortHeuristic};
+use crate::spritesheet::{SpritesheetBuildConfig, SpritesheetError};
+use crate::{AnimationLoopMode, AtlasAnimation, AtlasFrameSlice};
+
+/// Errors that can occur while packing an atlas from a config.
+#[derive(Debug, Error)]
+pub enum GenerationError {
+    /// A pattern of the config is not a valid glob pattern.
+    #[error("invalid pattern: {0}")]
+    InvalidPattern(#[from] glob::PatternError),
+    /// The images could not be packed into an atlas.
+    #[error(transparent)]
+    Spritesheet(#[from] SpritesheetError),
+}
+
+/// Packing options of a `.rpack_gen.json` file, output options used only by `rpack_cli` are ignored.
+///
+/// Defaults are the same as in `rpack_cli`, so the packed atlas matches the one it generates.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct AtlasGenConfig {
+    /// Glob patterns of the source images, relative to the config.
+    pub asset_patterns: Vec<String>,
+    /// Size of the atlas pages, the upper bound when `auto_size` is enabled. Default value is `2048`.
+    #[serde(default)]
+    pub size: Option<u32>,
+    /// Width of the atlas pages overriding `size`.
+    #[serde(default)]
+    pub max_width: Option<u32>,
+    /// Height of the atlas pages overriding `size`.
+    #[serde(default)]
+    pub max_height: Option<u32>,
+    /// Shrinks the pages to the smallest size fitting all images. Default value is `false`.
+    #[serde(default)]
+    pub auto_size: Option<bool>,
+    /// Limits the automatic size to powers of two. Default value is `true`.
+    #[serde(default)]
+    pub power_of_two: Option<bool>,
+    /// Allows the automatic size to have different width and height. Default value is `false`.
+    #[serde(default)]
+    pub allow_non_square: Option<bool>,
+    /// Size of the padding between frames in pixel. Default value is `2`.
+    #[serde(default)]
+    pub texture_padding: Option<u32>,
+    /// Size of the padding on the outer edge of the pages in pixel. Default value is `0`.
+    #[serde(default)]
+    pub border_padding: Option<u32>,
+    /// Size of the area around each frame filled according to `extrusion_fill` in pixel. Default value is `0`.
+    #[serde(default)]
+    pub texture_extrusion: Option<u32>,
+    /// Defines how the extrusion area is filled. Default value is `EdgeRepeat`.
+    #[serde(default)]
+    pub extrusion_fill: Option<ExtrusionFill>,
+    /// Places images that do not fit into a single page on additional pages. Default value is `false`.
+    #[serde(default)]
+    pub allow_multiple_pages: Option<bool>,
+    /// Removes transparent borders of images before packing. Default value is `false`.
+    #[serde(default)]
+    pub trim: Option<bool>,
+    /// Pixels with alpha lower or equal to this value are treated as transparent during trimming. Default value is `0`.
+    #[serde(default)]
+    pub trim_alpha_threshold: Option<u8>,
+    /// Allows rotating images by 90 degrees when it lets them fit better. Default value is `false`.
+    #[serde(default)]
+    pub allow_rotation: Option<bool>,
+    /// Algorithm used for placing images in the atlas. Default value is `Skyline`.
+    #[serde(default)]
+    pub packing_algorithm: Option<PackingAlgorithm>,
+    /// Order in which images are packed. Default value is `None`, keeping the sorted file paths order.
+    #[serde(default)]
+    pub sort_heuristic: Option<SortHeuristic>,
+    /// Packs pixel-identical images once, all their keys point at the same area. Default value is `false`.
+    #[serde(default)]
+    pub deduplicate: Option<bool>,
+    /// Nine-slice borders of images with keys matching the patterns, first matching pattern is used.
+    ///
+    /// Borders from a `.slice.json` file next to the image take precedence.
+    #[serde(default)]
+    pub nine_slices: Vec<NineSliceConfig>,
+    /// Pivot points of images with keys matching the patterns, first matching pattern is used.
+    ///
+    /// Pivot from a `.pivot.json` file next to the image takes precedence.
+    #[serde(default)]
+    pub pivots: Vec<PivotConfig>,
+    /// Groups keys ending with a frame number, like `run_000`, into animations. Default value is `false`.
+    #[serde(default)]
+    pub detect_animations: Option<bool>,
+    /// Duration of animation frames in milliseconds. Default value is `100`.
+    #[serde(default)]
+    pub animation_frame_duration: Option<u32>,
+    /// Explicitly defined animations, replacing detected ones with the same name.
+    #[serde(default)]
+    pub animations: Vec<AnimationConfig>,
+}
+
+impl AtlasGenConfig {
+    /// Returns nine-slice borders from the first `nine_slices` pattern matching the image `key`.
+    pub fn nine_slice_for(&self, key: &str) -> Result<Option<AtlasFrameSlice>, GenerationError> {
+        for nine_slice in self.nine_slices.iter() {
+            if glob::Pattern::new(&nine_slice.pattern)?.matches(key) {
+                return Ok(Some(nine_slice.border));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the pivot from the first `pivots` pattern matching the image `key`.
+    pub fn pivot_for(&self, key: &str) -> Result<Option<[f32; 2]>, GenerationError> {
+        for pivot in self.pivots.iter() {
+            if glob::Pattern::new(&pivot.pattern)?.matches(key) {
+                return Ok(Some(pivot.pivot));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Options without nine-slices and pivots of images, they are added per image by the caller.
+impl From<&AtlasGenConfig> for SpritesheetBuildConfig {
+    fn from(config: &AtlasGenConfig) -> Self {
+        SpritesheetBuildConfig {
+            packer_config: TexturePackerConfig {
+                max_width: config.max_width.or(config.size).unwrap_or(2048),
+                max_height: config.max_height.or(config.size).unwrap_or(2048),
+                allow_rotation: config.allow_rotation.unwrap_or_default(),
+                force_max_dimensions: true,
+                border_padding: config.border_padding.unwrap_or(0),
+                texture_padding: config.texture_padding.unwrap_or(2),
+                texture_extrusion: config.texture_extrusion.unwrap_or(0),
+                trim: false,
+                texture_outlines: false,
+            },
+            skip_metadata_serialization: false,
+            allow_multiple_pages: config.allow_multiple_pages.unwrap_or_default(),
+            trim: config.trim.unwrap_or_default(),
+            trim_alpha_threshold: config.trim_alpha_threshold.unwrap_or_default(),
+            extrusion_fill: config.extrusion_fill.unwrap_or_default(),
+            counter_clockwise: false,
+            packing_algorithm: config.packing_algorithm.unwrap_or_default(),
+            auto_size: config
+                .auto_size
+                .unwrap_or_default()
+                .then(|| AutoSizeConfig {
+                    power_of_two: config.power_of_two.unwrap_or(true),
+                    allow_non_square: config.allow_non_square.unwrap_or_default(),
+                }),
+            sort_heuristic: config.sort_heuristic.unwrap_or_default(),
+            deduplicate: config.deduplicate.unwrap_or_default(),
+            nine_slices: HashMap::new(),
+            pivots: HashMap::new(),
+            detect_animations: config.detect_animations.unwrap_or_default(),
+            animation_frame_duration: config
+                .animation_frame_duration
+                .unwrap_or(AtlasAnimation::DEFAULT_FRAME_DURATION),
+            animations: config.animations.clone(),
+        }
+    }
+}
+
+/// Nine-slice borders for images with keys matching `pattern`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct NineSliceConfig {
+    /// Glob pattern matched against the image key, for example `ui/panels/*`.
+    pub pattern: String,
+    /// Borders of the matching images.
+    #[serde(flatten)]
+    pub border: AtlasFrameSlice,
+}
+
+/// Pivot point for images with keys matching `pattern`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct PivotConfig {
+    /// Glob pattern matched against the image key, for example `characters/**`.
+    pub pattern: String,
+    /// Pivot relative to the image size, `[0, 0]` is the top-left and `[1, 1]` the bottom-right corner.
+    pub pivot: [f32; 2],
+}
+
+/// Explicit definition of an animation made of atlas frames.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct AnimationConfig {
+    /// Name of the animation, replaces a detected animation with the same name.
+    pub name: String,
+    /// Keys of the frames in playback order.
+    pub frames: Vec<String>,
+    /// Duration of every frame in milliseconds, the last value is used for the remaining frames.
+    /// Defaults to `animation_frame_duration`.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub durations: Vec<u32>,
+    /// Default value is `Loop`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub loop_mode: Option<AnimationLoopMode>,
+}
+
+impl AnimationConfig {
+    /// Creates the animation, frames without a duration use `frame_duration`.
+    pub fn to_animation(&self, frame_duration: u32) -> AtlasAnimation {
+        let durations = (0..self.frames.len())
+            .map(|i| {
+                self.durations
+                    .get(i)
+                    .or(self.durations.last())
+                    .copied()
+                    .unwrap_or(frame_duration)
+            })
+            .collect();
+        AtlasAnimation {
+            frames: self.frames.clone(),
+            durations,
+            loop_mode: self.loop_mode.unwrap_or_default(),
+        }
+    }
+}
+
+/// Splits `key` into the sequence name and frame number, `hero/run_003` becomes `("hero/run", 3)`.
+///
+/// Separators before the number are removed, so `explosion/01` belongs to the `explosion` sequence.
+pub fn sequence_frame(key: &str) -> Option<(&str, u64)> {
+    let name = key.trim_end_matches(|c: char| c.is_ascii_digit());
+    let number = key[name.len()..].parse().ok()?;
+    let name = name.trim_end_matches(['_', '-', '.', ' ', '/']);
+    if name.is_empty() {
+        return None;
+    }
+    Some((name, number))
+}
+
+/// Groups keys ending with a frame number into looping animations of at least two frames.
+pub fn detect_animations<'a>(
+    keys: impl IntoIterator<Item = &'a str>,
+    frame_duration: u32,
+) -> BTreeMap<String, AtlasAnimation> {
+    let mut sequences: HashMap<&str, Vec<(u64, &str)>> = HashMap::new();
+    for key in keys {
+        if let Some((name, number)) = sequence_frame(key) {
+            sequences.entry(name).or_default().push((number, key));
+        }
+    }
+    sequences
+        .into_iter()
+        .filter(|(_, frames)| frames.len() > 1)
+        .map(|(name, mut frames)| {
+            frames.sort();
+            let animation = AtlasAnimation {
+                durations: vec![frame_duration; frames.len()],
+                frames: frames.into_iter().map(|(_, key)| key.to_owned()).collect(),
+                loop_mode: AnimationLoopMode::Loop,
+            };
+            (name.to_owned(), animation)
+        })
+        .collect()
+}
+
+/// Returns the frame key of the image with the relative path `id`, used by `rpack_cli` as well.
+///
+/// Backslashes are replaced with slashes and everything after the first dot is removed.
+pub fn frame_key(id: &str) -> String {
+    let id = id.replace('\\', "/");
+    match id.split_once('.') {
+        Some((key, _)) => key.to_owned(),
+        None => id,
+    }
+}
+
+/// Returns frame keys for image paths, made with [`frame_key`] from the paths without their common directory.
+pub fn frame_keys(paths: &[String]) -> Vec<String> {
+    let paths: Vec<String> = paths.iter().map(|p| p.replace('\\', "/")).collect();
+    let mut prefix = paths
+        .first()
+        .and_then(|p| p.rfind('/').map(|i| &p[..=i]))
+        .unwrap_or_default();
+    for path in paths.iter().skip(1) {
+        while !path.starts_with(prefix) {
+            prefix = prefix[..prefix.len() - 1]
+                .rfind('/')
+                .map_or("", |i| &prefix[..=i]);
+        }
+    }
+    paths
+        .iter()
+        .map(|path| frame_key(&path[prefix.len()..]))
+        .collect()
+}
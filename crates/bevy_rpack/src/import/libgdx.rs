@@ -0,0 +1,228 @@
+use alloc::collections::BTreeMap;
+
+use super::{ImportError, animation, atlas_asset};
+use crate::{AtlasFrame, AtlasFrameSlice, AtlasFrameTrim, AtlasPage, SerializableRect};
+
+/// Region as described in the file, positions use the top-left origin and sizes are given before rotation.
+struct Region {
+    name: String,
+    page: usize,
+    line: usize,
+    position: [u32; 2],
+    size: [u32; 2],
+    /// Offset of the trimmed image from the bottom-left corner of the original image.
+    offset: [u32; 2],
+    original_size: Option<[u32; 2]>,
+    degrees: u32,
+    split: Option<[u32; 4]>,
+    index: i32,
+}
+
+/// Parses libGDX and Spine `.atlas` files, both the legacy (`xy`, `size`, `orig`, `offset`)
+/// and the current (`bounds`, `offsets`) region fields are supported.
+///
+/// Regions with an index become `name_index` frames grouped into a `name` animation.
+/// Regions rotated by 90 degrees, written by libGDX and Spine as `rotate: true`, are rotated counter-clockwise,
+/// regions rotated by 270 degrees clockwise like in rpack atlases.
+pub fn from_libgdx_atlas(content: &str) -> Result<crate::AtlasAsset, ImportError> {
+    let mut pages: Vec<(AtlasPage, usize)> = Vec::new();
+    let mut regions: Vec<Region> = Vec::new();
+    let mut in_page = false;
+    for (i, line) in content.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            in_page = false;
+            continue;
+        }
+        let Some((field, value)) = line.split_once(':') else {
+            if in_page {
+                regions.push(Region {
+                    name: line.to_owned(),
+                    page: pages.len() - 1,
+                    line: line_number,
+                    position: [0, 0],
+                    size: [0, 0],
+                    offset: [0, 0],
+                    original_size: None,
+                    degrees: 0,
+                    split: None,
+                    index: -1,
+                });
+            } else {
+                pages.push((
+                    AtlasPage {
+                        size: [0, 0],
+                        filename: line.to_owned(),
+                    },
+                    line_number,
+                ));
+                in_page = true;
+            }
+            continue;
+        };
+        let field = field.trim();
+        let value = value.trim();
+        let syntax_error = |message: String| ImportError::Syntax {
+            line: line_number,
+            message,
+        };
+        let numbers = |count: usize| -> Result<Vec<u32>, ImportError> {
+            let numbers: Vec<u32> = value
+                .split(',')
+                .map(|v| v.trim().parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| syntax_error(format!("invalid {field} value: {value}")))?;
+            if numbers.len() < count {
+                return Err(syntax_error(format!(
+                    "expected {count} values for {field}, got {value}"
+                )));
+            }
+            Ok(numbers)
+        };
+        let page_has_regions = regions
+            .last()
+            .is_some_and(|r| in_page && r.page + 1 == pages.len());
+        if !page_has_regions {
+            let Some((page, _)) = pages.last_mut().filter(|_| in_page) else {
+                return Err(syntax_error(format!("{field} defined outside of a page")));
+            };
+            if field == "size" {
+                let size = numbers(2)?;
+                page.size = [size[0], size[1]];
+            }
+            continue;
+        }
+        let Some(region) = regions.last_mut() else {
+            continue;
+        };
+        match field {
+            "bounds" => {
+                let bounds = numbers(4)?;
+                region.position = [bounds[0], bounds[1]];
+                region.size = [bounds[2], bounds[3]];
+            }
+            "xy" => {
+                let xy = numbers(2)?;
+                region.position = [xy[0], xy[1]];
+            }
+            "size" => {
+                let size = numbers(2)?;
+                region.size = [size[0], size[1]];
+            }
+            "offsets" => {
+                let offsets = numbers(4)?;
+                region.offset = [offsets[0], offsets[1]];
+                region.original_size = Some([offsets[2], offsets[3]]);
+            }
+            "offset" => {
+                let offset = numbers(2)?;
+                region.offset = [offset[0], offset[1]];
+            }
+            "orig" => {
+                let original_size = numbers(2)?;
+                region.original_size = Some([original_size[0], original_size[1]]);
+            }
+            "rotate" => {
+                region.degrees = match value {
+                    "true" => 90,
+                    "false" => 0,
+                    _ => numbers(1)?[0],
+                };
+            }
+            "split" => {
+                let split = numbers(4)?;
+                region.split = Some([split[0], split[1], split[2], split[3]]);
+            }
+            "index" => {
+                region.index = value
+                    .parse()
+                    .map_err(|_| syntax_error(format!("invalid index: {value}")))?;
+            }
+            _ => {}
+        }
+    }
+
+    if let Some((page, line)) = pages.iter().find(|(p, _)| p.size[0] == 0 || p.size[1] == 0) {
+        return Err(ImportError::Syntax {
+            line: *line,
+            message: format!("page {} has no size", page.filename),
+        });
+    }
+    let mut sequences: BTreeMap<String, Vec<(i32, String)>> = BTreeMap::new();
+    let mut frames = Vec::with_capacity(regions.len());
+    for region in regions {
+        let (rotated, counter_clockwise) = match region.degrees {
+            0 => (false, false),
+            90 => (true, true),
+            270 => (true, false),
+            degrees => {
+                return Err(ImportError::Syntax {
+                    line: region.line,
+                    message: format!(
+                        "region {} is rotated by {degrees} degrees, only 90 and 270 are supported",
+                        region.name
+                    ),
+                });
+            }
+        };
+        let [w, h] = region.size;
+        let original_size = region.original_size.unwrap_or(region.size);
+        let trim =
+            (original_size != region.size || region.offset != [0, 0]).then(|| AtlasFrameTrim {
+                offset: [
+                    region.offset[0],
+                    original_size[1].saturating_sub(region.offset[1] + h),
+                ],
+                source_size: original_size,
+            });
+        let key = if region.index >= 0 {
+            let key = format!("{}_{}", region.name, region.index);
+            sequences
+                .entry(region.name.clone())
+                .or_default()
+                .push((region.index, key.clone()));
+            key
+        } else {
+            region.name
+        };
+        let (frame_w, frame_h) = if rotated { (h, w) } else { (w, h) };
+        frames.push(AtlasFrame {
+            key,
+            frame: SerializableRect {
+                x: region.position[0],
+                y: region.position[1],
+                w: frame_w,
+                h: frame_h,
+            },
+            page: region.page,
+            trim,
+            rotated,
+            counter_clockwise,
+            slice: region
+                .split
+                .map(|[left, right, top, bottom]| AtlasFrameSlice {
+                    left,
+                    right,
+                    top,
+                    bottom,
+                }),
+            pivot: None,
+        });
+    }
+    let animations = sequences
+        .into_iter()
+        .map(|(name, mut frames)| {
+            frames.sort();
+            (
+                name,
+                animation(frames.into_iter().map(|(_, key)| key).collect()),
+            )
+        })
+        .collect();
+    atlas_asset(
+        pages.into_iter().map(|(page, _)| page).collect(),
+        frames,
+        animations,
+    )
+}
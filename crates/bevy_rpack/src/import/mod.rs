@@ -0,0 +1,89 @@
+//! Conversion of atlases created by other tools into [`AtlasAsset`].
+
+use alloc::collections::BTreeMap;
+
+use thiserror::Error;
+
+use crate::{AnimationLoopMode, AtlasAnimation, AtlasAsset, AtlasFrame, AtlasPage};
+
+mod libgdx;
+mod texture_packer;
+
+pub use libgdx::from_libgdx_atlas;
+pub use texture_packer::from_texture_packer_json;
+
+/// Errors that can occur while importing atlases created by other tools.
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum ImportError {
+    /// The TexturePacker JSON file could not be parsed.
+    #[error("could not parse TexturePacker JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    /// The libGDX atlas contains a line that could not be parsed.
+    #[error("line {line}: {message}")]
+    Syntax {
+        /// Number of the line, starting from `1`.
+        line: usize,
+        /// Description of the problem.
+        message: String,
+    },
+    /// The atlas has no pages or frames.
+    #[error("atlas is empty")]
+    Empty,
+    /// The file extension does not match any supported format.
+    #[error("unsupported atlas format: {0}")]
+    UnsupportedFormat(String),
+}
+
+/// Parses the atlas, choosing the format by the `filename` extension.
+///
+/// `.atlas` files are read as libGDX atlases, other `.json` files as TexturePacker JSON.
+pub fn from_file_content(filename: &str, content: &str) -> Result<AtlasAsset, ImportError> {
+    if filename.ends_with(".atlas") {
+        from_libgdx_atlas(content)
+    } else if filename.ends_with(".json") {
+        from_texture_packer_json(content)
+    } else {
+        Err(ImportError::UnsupportedFormat(filename.to_owned()))
+    }
+}
+
+/// Removes the image extension from frame names, rpack keys do not contain it.
+fn frame_key(name: &str) -> String {
+    const IMAGE_EXTENSIONS: [&str; 7] = ["png", "jpg", "jpeg", "bmp", "tga", "webp", "gif"];
+    match name.rsplit_once('.') {
+        Some((key, extension))
+            if IMAGE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()) =>
+        {
+            key.to_owned()
+        }
+        _ => name.to_owned(),
+    }
+}
+
+/// Creates a looping animation with the default frame duration.
+fn animation(frames: Vec<String>) -> AtlasAnimation {
+    AtlasAnimation {
+        durations: vec![AtlasAnimation::DEFAULT_FRAME_DURATION; frames.len()],
+        frames,
+        loop_mode: AnimationLoopMode::Loop,
+    }
+}
+
+/// Creates the asset, the first page is described by `size` and `filename`.
+fn atlas_asset(
+    pages: Vec<AtlasPage>,
+    mut frames: Vec<AtlasFrame>,
+    animations: BTreeMap<String, AtlasAnimation>,
+) -> Result<AtlasAsset, ImportError> {
+    let first = pages.first().cloned().ok_or(ImportError::Empty)?;
+    frames.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok(AtlasAsset {
+        size: first.size,
+        filename: first.filename,
+        frames,
+        pages: if pages.len() > 1 { pages } else { Vec::new() },
+        animations,
+        metadata: Default::default(),
+    })
+}
@@ -0,0 +1,124 @@
+use alloc::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use super::{ImportError, animation, atlas_asset, frame_key};
+use crate::{AtlasFrame, AtlasFrameTrim, AtlasPage, SerializableRect};
+
+#[derive(Deserialize)]
+struct Sheet {
+    frames: Frames,
+    meta: Meta,
+    #[serde(default)]
+    animations: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Frames {
+    Hash(BTreeMap<String, Frame>),
+    Array(Vec<Frame>),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Frame {
+    #[serde(default)]
+    filename: Option<String>,
+    frame: Rect,
+    #[serde(default)]
+    rotated: bool,
+    #[serde(default)]
+    trimmed: bool,
+    sprite_source_size: Option<Rect>,
+    source_size: Option<Size>,
+    pivot: Option<Point>,
+}
+
+#[derive(Deserialize)]
+struct Meta {
+    image: String,
+    size: Size,
+}
+
+#[derive(Deserialize)]
+struct Rect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize)]
+struct Size {
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize)]
+struct Point {
+    x: f32,
+    y: f32,
+}
+
+/// Parses a single page of the TexturePacker JSON hash or array format.
+///
+/// Multi-pack atlases have to be imported page by page.
+pub fn from_texture_packer_json(content: &str) -> Result<crate::AtlasAsset, ImportError> {
+    let sheet: Sheet = serde_json::from_str(content)?;
+    let frames: Vec<(String, Frame)> = match sheet.frames {
+        Frames::Hash(frames) => frames.into_iter().collect(),
+        Frames::Array(frames) => frames
+            .into_iter()
+            .map(|f| (f.filename.clone().unwrap_or_default(), f))
+            .collect(),
+    };
+    let frames = frames
+        .into_iter()
+        .map(|(name, f)| {
+            // Frame size is given before rotation, the area in the atlas is swapped
+            let (w, h) = if f.rotated {
+                (f.frame.h, f.frame.w)
+            } else {
+                (f.frame.w, f.frame.h)
+            };
+            let trim = match (f.trimmed, f.sprite_source_size, f.source_size) {
+                (true, Some(offset), Some(source)) => Some(AtlasFrameTrim {
+                    offset: [offset.x, offset.y],
+                    source_size: [source.w, source.h],
+                }),
+                _ => None,
+            };
+            AtlasFrame {
+                key: frame_key(&name),
+                frame: SerializableRect {
+                    x: f.frame.x,
+                    y: f.frame.y,
+                    w,
+                    h,
+                },
+                page: 0,
+                trim,
+                rotated: f.rotated,
+                counter_clockwise: false,
+                slice: None,
+                pivot: f.pivot.map(|p| [p.x, p.y]),
+            }
+        })
+        .collect();
+    let animations = sheet
+        .animations
+        .into_iter()
+        .map(|(name, frames)| {
+            (
+                name,
+                animation(frames.iter().map(|f| frame_key(f)).collect()),
+            )
+        })
+        .collect();
+    let page = AtlasPage {
+        size: [sheet.meta.size.w, sheet.meta.size.h],
+        filename: sheet.meta.image,
+    };
+    atlas_asset(vec![page], frames, animations)
+}
@@ -0,0 +1,154 @@
+use crate::plugin::{RpackAtlasAsset, RpackAtlasError};
+use bevy_asset::{AssetEvent, AssetId, AssetServer, Assets};
+use bevy_ecs::message::MessageReader;
+use bevy_ecs::resource::Resource;
+use bevy_ecs::system::{Res, ResMut};
+use bevy_log::warn;
+use bevy_platform::collections::HashMap;
+
+/// Index of the frame keys of all loaded [`RpackAtlasAsset`]s, kept up to date by the [`RpackAssetPlugin`](crate::prelude::RpackAssetPlugin).
+///
+/// Keys can be qualified with the name of the atlas, which is its asset path without extensions,
+/// so `ui/icons/close` is the `close` frame of the `ui/icons.rpack.json` atlas.
+/// Keys found in more than one atlas can only be looked up when qualified.
+#[derive(Resource, Default, Debug)]
+pub struct RpackAtlasKeyIndex {
+    /// Atlases containing each key.
+    keys: HashMap<String, Vec<AssetId<RpackAtlasAsset>>>,
+    /// Atlases by their names.
+    names: HashMap<String, AssetId<RpackAtlasAsset>>,
+    /// Name and keys of every indexed atlas.
+    atlases: HashMap<AssetId<RpackAtlasAsset>, (Option<String>, Vec<String>)>,
+}
+
+impl RpackAtlasKeyIndex {
+    /// Returns the atlas containing the `key` and the key of the frame within that atlas.
+    ///
+    /// Keys that are not found are looked up as `atlas_name/key`.
+    pub fn resolve<'a>(
+        &self,
+        key: &'a str,
+    ) -> Result<(AssetId<RpackAtlasAsset>, &'a str), RpackAtlasError> {
+        if self.atlases.is_empty() {
+            return Err(RpackAtlasError::NoAtlas);
+        }
+        if let Some(ids) = self.keys.get(key) {
+            return match ids.as_slice() {
+                [id] => Ok((*id, key)),
+                _ => Err(RpackAtlasError::AmbiguousKey(key.to_owned())),
+            };
+        }
+        for (separator, _) in key.match_indices('/') {
+            let (name, frame_key) = (&key[..separator], &key[separator + 1..]);
+            if let Some(id) = self.get_atlas(name)
+                && self
+                    .keys
+                    .get(frame_key)
+                    .is_some_and(|ids| ids.contains(&id))
+            {
+                return Ok((id, frame_key));
+            }
+        }
+        Err(RpackAtlasError::WrongKey)
+    }
+
+    /// Returns the atlas with the given name, which is its asset path without extensions.
+    pub fn get_atlas(&self, name: &str) -> Option<AssetId<RpackAtlasAsset>> {
+        self.names.get(name).copied()
+    }
+
+    /// Returns true if the keys of the atlas are indexed.
+    pub fn contains_atlas(&self, id: AssetId<RpackAtlasAsset>) -> bool {
+        self.atlases.contains_key(&id)
+    }
+
+    /// Returns true if the key is found in more than one atlas.
+    pub fn is_ambiguous(&self, key: &str) -> bool {
+        self.keys.get(key).is_some_and(|ids| ids.len() > 1)
+    }
+
+    /// Provides all keys of the indexed atlases, keys found in more than one atlas are listed once.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.keys.keys().map(String::as_str)
+    }
+
+    /// Adds the keys of the `atlas`, replacing keys indexed for it before.
+    fn insert(
+        &mut self,
+        id: AssetId<RpackAtlasAsset>,
+        name: Option<String>,
+        atlas: &RpackAtlasAsset,
+    ) {
+        self.remove(id);
+        let keys: Vec<String> = atlas.files.keys().cloned().collect();
+        for key in keys.iter() {
+            let ids = self.keys.entry(key.clone()).or_default();
+            ids.push(id);
+            if ids.len() == 2 {
+                warn!(
+                    "Frame key {key} is found in more than one atlas, qualify it with the atlas name to look it up"
+                );
+            }
+        }
+        if let Some(name) = name.as_ref()
+            && self.names.insert(name.clone(), id).is_some()
+        {
+            warn!("Atlas name {name} is used by more than one atlas");
+        }
+        self.atlases.insert(id, (name, keys));
+    }
+
+    /// Removes the keys of the atlas.
+    fn remove(&mut self, id: AssetId<RpackAtlasAsset>) {
+        let Some((name, keys)) = self.atlases.remove(&id) else {
+            return;
+        };
+        for key in keys {
+            if let Some(ids) = self.keys.get_mut(&key) {
+                ids.retain(|i| *i != id);
+                if ids.is_empty() {
+                    self.keys.remove(&key);
+                }
+            }
+        }
+        if let Some(name) = name
+            && self.names.get(&name) == Some(&id)
+        {
+            self.names.remove(&name);
+        }
+    }
+}
+
+/// Returns the name of the atlas loaded from the `path`, the path without extensions.
+fn atlas_name(path: &str) -> String {
+    let file_start = path.rfind('/').map_or(0, |i| i + 1);
+    match path[file_start..].find('.') {
+        Some(dot) => path[..file_start + dot].to_owned(),
+        None => path.to_owned(),
+    }
+}
+
+/// Updates the [`RpackAtlasKeyIndex`] when atlases are added, modified or removed.
+pub fn update_rpack_atlas_key_index(
+    mut events: MessageReader<AssetEvent<RpackAtlasAsset>>,
+    mut index: ResMut<RpackAtlasKeyIndex>,
+    atlases: Res<Assets<RpackAtlasAsset>>,
+    asset_server: Option<Res<AssetServer>>,
+) {
+    for event in events.read() {
+        match *event {
+            AssetEvent::Added { id } | AssetEvent::Modified { id } => {
+                let Some(atlas) = atlases.get(id) else {
+                    continue;
+                };
+                let name = asset_server
+                    .as_ref()
+                    .and_then(|server| server.get_path(id))
+                    .map(|path| atlas_name(&path.path().to_string_lossy().replace('\\', "/")));
+                index.insert(id, name, atlas);
+            }
+            AssetEvent::Removed { id } => index.remove(id),
+            _ => {}
+        }
+    }
+}
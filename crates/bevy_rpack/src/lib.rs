@@ -1,6 +1,7 @@
 #![doc = include_str!("../README.md")]
 extern crate alloc;
 use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
 #[cfg(feature = "bevy")]
 use bevy_asset::Asset;
 #[cfg(feature = "bevy")]
@@ -10,22 +11,68 @@ use bevy_reflect::Reflect;
 /// Contains the Bevy plugin for handling `Rpack` assets and atlases.
 mod plugin;
 
-mod packer;
+#[cfg(feature = "bevy")]
+/// Contains the opt-in plugin playing atlas animations.
+mod animation;
+
+#[cfg(feature = "bevy")]
+/// Contains the atlas packed at runtime from added images.
+mod dynamic;
+
+#[cfg(feature = "bevy")]
+/// Contains the index of frame keys of all loaded atlases.
+mod index;
+
+#[cfg(feature = "bevy")]
+/// Contains the asset processor packing `.rpack_gen.json` atlases ahead of time.
+mod processor;
+
+/// Contains drawing images into atlas pages shared with `rpack_cli`.
+pub mod compositing;
+pub mod generation;
+pub mod import;
+/// Contains building atlases from images shared with `rpack_cli`.
+pub mod spritesheet;
+
+/// Contains the packing algorithms shared by runtime atlases, the asset processor and `rpack_cli`.
+pub mod packer;
 
 /// Re-exports all types for working with texture atlases.
 pub mod prelude {
+    #[cfg(feature = "bevy")]
+    /// Provides playback of atlas animations on sprites and image nodes.
+    pub use super::animation::{
+        RpackAnimation, RpackAnimationFinished, RpackAnimationPlugin, advance_rpack_animations,
+    };
+    #[cfg(feature = "bevy")]
+    /// Provides packing atlases at runtime from added images.
+    pub use super::dynamic::{
+        DynamicRpackAtlas, DynamicRpackAtlasError, pack_dynamic_atlas_images,
+    };
+    #[cfg(feature = "bevy")]
+    /// Provides lookups of frame keys across all loaded atlases.
+    pub use super::index::{RpackAtlasKeyIndex, update_rpack_atlas_key_index};
     #[cfg(feature = "bevy")]
     /// Provides easy access to `Rpack` asset-related functionality in a Bevy application.
     pub use super::plugin::{
         RpackAssetHelper, RpackAssetPlugin, RpackAtlasAsset, RpackAtlasAssetError,
-        RpackAtlasAssetLoader, RpackAtlasAssetLoaderSettings, RpackAtlasError, RpackAtlases,
+        RpackAtlasAssetLoader, RpackAtlasAssetLoaderSettings, RpackAtlasError,
+        RpackAtlasGenConfigLoader, RpackAtlasPage, RpackAtlases,
+    };
+    #[cfg(feature = "bevy")]
+    /// Provides packing atlases during asset processing.
+    pub use super::processor::{
+        RpackAtlasAssetSaver, RpackAtlasAssetSaverSettings, RpackAtlasGenProcessor,
     };
     /// Re-exports core types for working with texture atlases.
-    pub use super::{AtlasAsset, AtlasFrame, SerializableRect};
+    pub use super::{
+        AnimationLoopMode, AtlasAnimation, AtlasAsset, AtlasFrame, AtlasFrameSlice, AtlasFrameTrim,
+        AtlasPage, SerializableRect,
+    };
 }
 
 /// Defines a rectangle in pixels with the origin at the top-left of the texture atlas.
-#[derive(Copy, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
 #[cfg_attr(feature = "bevy", derive(Reflect))]
 pub struct SerializableRect {
     /// Horizontal position the rectangle begins at.
@@ -46,6 +93,95 @@ pub struct AtlasFrame {
     pub key: String,
     /// The rectangular area of the frame within the texture atlas.
     pub frame: SerializableRect,
+    /// Index of the atlas page containing the frame, `0` for single page atlases.
+    #[cfg_attr(feature = "bevy", reflect(default))]
+    #[serde(default, skip_serializing_if = "is_first_page")]
+    pub page: usize,
+    /// Describes how the frame was trimmed, `None` when frame contains the whole source image.
+    #[cfg_attr(feature = "bevy", reflect(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trim: Option<AtlasFrameTrim>,
+    /// Whether the frame was rotated 90 degrees clockwise when packed into the atlas.
+    ///
+    /// The `frame` rectangle describes the area taken in the atlas, so width and height of rotated frames are swapped.
+    #[cfg_attr(feature = "bevy", reflect(default))]
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub rotated: bool,
+    /// Whether a rotated frame was rotated 90 degrees counter-clockwise instead, like the regions of libGDX atlases.
+    #[cfg_attr(feature = "bevy", reflect(default))]
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub counter_clockwise: bool,
+    /// Nine-slice borders of the frame, `None` for frames that are not sliced.
+    #[cfg_attr(feature = "bevy", reflect(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slice: Option<AtlasFrameSlice>,
+    /// Pivot point relative to the source image size, `[0, 0]` is the top-left and `[1, 1]` the bottom-right corner.
+    ///
+    /// `None` means the center of the source image.
+    #[cfg_attr(feature = "bevy", reflect(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pivot: Option<[f32; 2]>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// Information about transparent borders removed from the source image before packing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect))]
+pub struct AtlasFrameTrim {
+    /// Position of the trimmed frame within the source image in pixels (x, y).
+    pub offset: [u32; 2],
+    /// The dimensions of the source image before trimming in pixels (width, height).
+    pub source_size: [u32; 2],
+}
+
+/// Insets in pixels dividing a frame into nine parts for nine-slice scaling.
+///
+/// Insets are measured from the edges of the frame in its original orientation.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect))]
+pub struct AtlasFrameSlice {
+    /// Inset from the left edge.
+    pub left: u32,
+    /// Inset from the right edge.
+    pub right: u32,
+    /// Inset from the top edge.
+    pub top: u32,
+    /// Inset from the bottom edge.
+    pub bottom: u32,
+}
+
+impl AtlasFrameSlice {
+    /// Converts insets defined for the source image to insets of the frame with `size` left after trimming.
+    pub fn trimmed(&self, trim: Option<&AtlasFrameTrim>, size: [u32; 2]) -> Self {
+        let Some(trim) = trim else {
+            return *self;
+        };
+        let right_trim = trim.source_size[0].saturating_sub(trim.offset[0] + size[0]);
+        let bottom_trim = trim.source_size[1].saturating_sub(trim.offset[1] + size[1]);
+        Self {
+            left: self.left.saturating_sub(trim.offset[0]),
+            right: self.right.saturating_sub(right_trim),
+            top: self.top.saturating_sub(trim.offset[1]),
+            bottom: self.bottom.saturating_sub(bottom_trim),
+        }
+    }
+}
+
+fn is_first_page(page: &usize) -> bool {
+    *page == 0
+}
+
+/// Describes a single texture of an atlas split across multiple pages.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect))]
+pub struct AtlasPage {
+    /// The dimensions of the page texture in pixels (width, height).
+    pub size: [u32; 2],
+    /// The filename of the page texture.
+    pub filename: String,
 }
 
 /// Represents an entire texture atlas asset, including its metadata and frames.
@@ -58,12 +194,71 @@ pub struct AtlasAsset {
     pub filename: String,
     /// A collection of frames contained within the texture atlas.
     pub frames: Vec<AtlasFrame>,
+    /// All pages of the atlas, empty when the whole atlas fits into a single texture.
+    ///
+    /// The first page always matches `size` and `filename`.
+    #[cfg_attr(feature = "bevy", reflect(default))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pages: Vec<AtlasPage>,
+    /// Animations made of the atlas frames, keyed by their names.
+    #[cfg_attr(feature = "bevy", reflect(default))]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub animations: BTreeMap<String, AtlasAnimation>,
     /// Metadata about the atlas.
     #[cfg_attr(feature = "bevy", reflect(default))]
     #[serde(default, skip_serializing_if = "AtlasMetadata::skip_serialization")]
     pub metadata: AtlasMetadata,
 }
 
+/// Describes what happens after the last frame of an [`AtlasAnimation`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect))]
+#[serde(rename_all = "snake_case")]
+pub enum AnimationLoopMode {
+    /// Starts again from the first frame.
+    #[default]
+    Loop,
+    /// Plays the frames backwards, then forwards again.
+    PingPong,
+    /// Stops at the last frame.
+    Once,
+}
+
+/// Sequence of atlas frames played as an animation.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect))]
+pub struct AtlasAnimation {
+    /// Keys of the frames in playback order.
+    pub frames: Vec<String>,
+    /// Duration of every frame in milliseconds, matching `frames` by index.
+    pub durations: Vec<u32>,
+    /// What happens after the last frame.
+    #[cfg_attr(feature = "bevy", reflect(default))]
+    #[serde(default)]
+    pub loop_mode: AnimationLoopMode,
+}
+
+impl AtlasAnimation {
+    /// Duration of frames in milliseconds used when none is defined.
+    pub const DEFAULT_FRAME_DURATION: u32 = 100;
+
+    /// Returns the duration of the frame at `index` in milliseconds.
+    ///
+    /// Frames without an explicit duration reuse the last one.
+    pub fn duration(&self, index: usize) -> u32 {
+        self.durations
+            .get(index)
+            .or(self.durations.last())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the total duration of a single playback in milliseconds.
+    pub fn total_duration(&self) -> u32 {
+        (0..self.frames.len()).map(|i| self.duration(i)).sum()
+    }
+}
+
 /// Represents metadata associated with the texture atlas format.
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 #[cfg_attr(feature = "bevy", derive(Reflect))]
@@ -79,6 +274,48 @@ pub struct AtlasMetadata {
     pub skip_serialization: bool,
 }
 
+impl AtlasFrame {
+    /// Returns the size of the frame in its original orientation (width, height).
+    pub fn size(&self) -> [u32; 2] {
+        if self.rotated {
+            [self.frame.h, self.frame.w]
+        } else {
+            [self.frame.w, self.frame.h]
+        }
+    }
+
+    /// Returns the anchor that keeps the pivot of the source image in place,
+    /// expressed relative to the packed frame size (`-0.5..0.5`, `y` pointing up).
+    pub fn anchor(&self) -> [f32; 2] {
+        let [pivot_x, pivot_y] = self.pivot.unwrap_or([0.5, 0.5]);
+        let [w, h] = self.size().map(|v| v.max(1) as f32);
+        let (source_size, offset) = match self.trim {
+            Some(trim) => (
+                trim.source_size.map(|v| v as f32),
+                trim.offset.map(|v| v as f32),
+            ),
+            None => ([w, h], [0.0, 0.0]),
+        };
+        let x = source_size[0] * pivot_x - offset[0];
+        let y = source_size[1] * pivot_y - offset[1];
+        [x / w - 0.5, 0.5 - y / h]
+    }
+}
+
+impl AtlasAsset {
+    /// Returns all pages of the atlas, including the first one described by `size` and `filename`.
+    pub fn pages(&self) -> Vec<AtlasPage> {
+        if self.pages.is_empty() {
+            vec![AtlasPage {
+                size: self.size,
+                filename: self.filename.clone(),
+            }]
+        } else {
+            self.pages.clone()
+        }
+    }
+}
+
 impl AtlasMetadata {
     /// Returns true if the metadata should be skipped during serialization.
     pub fn skip_serialization(&self) -> bool {
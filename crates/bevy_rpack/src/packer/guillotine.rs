@@ -0,0 +1,78 @@
+use texture_packer::Rect;
+
+use super::{Packer, Placement};
+
+/// Packer splitting the free area with straight cuts, picking the free rectangle with the best area fit.
+pub struct GuillotinePacker {
+    allow_rotation: bool,
+    free: Vec<Rect>,
+}
+
+impl GuillotinePacker {
+    /// Creates an empty packer for a `width`x`height` area.
+    pub fn new(width: u32, height: u32, allow_rotation: bool) -> Self {
+        Self {
+            allow_rotation,
+            free: vec![Rect::new(0, 0, width, height)],
+        }
+    }
+
+    fn find_position(&self, w: u32, h: u32) -> Option<(usize, Placement)> {
+        let mut best: Option<(u64, usize, Placement)> = None;
+        for (i, free) in self.free.iter().enumerate() {
+            let mut candidates = vec![(w, h, false)];
+            if self.allow_rotation && w != h {
+                candidates.push((h, w, true));
+            }
+            for (cw, ch, rotated) in candidates {
+                if cw > free.w || ch > free.h {
+                    continue;
+                }
+                let leftover = free.w as u64 * free.h as u64 - cw as u64 * ch as u64;
+                if best.as_ref().is_none_or(|(score, _, _)| leftover < *score) {
+                    best = Some((
+                        leftover,
+                        i,
+                        Placement {
+                            x: free.x,
+                            y: free.y,
+                            rotated,
+                        },
+                    ));
+                }
+            }
+        }
+        best.map(|(_, i, placement)| (i, placement))
+    }
+}
+
+impl Packer for GuillotinePacker {
+    fn pack(&mut self, w: u32, h: u32) -> Option<Placement> {
+        let (index, placement) = self.find_position(w, h)?;
+        let (w, h) = if placement.rotated { (h, w) } else { (w, h) };
+        let free = self.free.swap_remove(index);
+        let leftover_w = free.w - w;
+        let leftover_h = free.h - h;
+
+        // Split along the shorter leftover axis, so the larger leftover area stays in one piece
+        let split_horizontally = leftover_w <= leftover_h;
+        let (right, bottom) = if split_horizontally {
+            (
+                Rect::new(free.x + w, free.y, leftover_w, h),
+                Rect::new(free.x, free.y + h, free.w, leftover_h),
+            )
+        } else {
+            (
+                Rect::new(free.x + w, free.y, leftover_w, free.h),
+                Rect::new(free.x, free.y + h, w, leftover_h),
+            )
+        };
+        self.free
+            .extend([right, bottom].into_iter().filter(|r| r.w > 0 && r.h > 0));
+        Some(placement)
+    }
+
+    fn can_pack(&self, w: u32, h: u32) -> bool {
+        self.find_position(w, h).is_some()
+    }
+}
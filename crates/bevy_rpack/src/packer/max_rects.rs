@@ -0,0 +1,203 @@
+use texture_packer::Rect;
+
+use super::{Packer, Placement};
+
+/// Rules used by [`MaxRectsPacker`] to choose a free rectangle for the next image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MaxRectsHeuristic {
+    /// Minimizes the shorter leftover side of the free rectangle.
+    BestShortSideFit,
+    /// Minimizes the longer leftover side of the free rectangle.
+    BestLongSideFit,
+    /// Picks the smallest free rectangle the image fits into.
+    BestAreaFit,
+    /// Places images as low and as far left as possible (Tetris style).
+    BottomLeft,
+    /// Maximizes the length of edges touching other images and the atlas border.
+    ContactPoint,
+}
+
+/// Packer keeping a list of maximal free rectangles, usually the densest of the available packers.
+pub struct MaxRectsPacker {
+    width: u32,
+    height: u32,
+    allow_rotation: bool,
+    heuristic: MaxRectsHeuristic,
+    free: Vec<Rect>,
+    used: Vec<Rect>,
+}
+
+impl MaxRectsPacker {
+    /// Creates an empty packer for a `width`x`height` area choosing free rectangles with `heuristic`.
+    pub fn new(
+        width: u32,
+        height: u32,
+        allow_rotation: bool,
+        heuristic: MaxRectsHeuristic,
+    ) -> Self {
+        Self {
+            width,
+            height,
+            allow_rotation,
+            heuristic,
+            free: vec![Rect::new(0, 0, width, height)],
+            used: Vec::new(),
+        }
+    }
+
+    /// Returns the score of placing `w`x`h` rectangle in `free`, lower is better.
+    fn score(&self, free: &Rect, w: u32, h: u32) -> (i64, i64) {
+        let leftover_w = (free.w - w) as i64;
+        let leftover_h = (free.h - h) as i64;
+        let short = leftover_w.min(leftover_h);
+        let long = leftover_w.max(leftover_h);
+        match self.heuristic {
+            MaxRectsHeuristic::BestShortSideFit => (short, long),
+            MaxRectsHeuristic::BestLongSideFit => (long, short),
+            MaxRectsHeuristic::BestAreaFit => (
+                (free.w as i64 * free.h as i64) - (w as i64 * h as i64),
+                short,
+            ),
+            MaxRectsHeuristic::BottomLeft => ((free.y + h) as i64, free.x as i64),
+            MaxRectsHeuristic::ContactPoint => {
+                (-(self.contact_score(free.x, free.y, w, h) as i64), 0)
+            }
+        }
+    }
+
+    fn contact_score(&self, x: u32, y: u32, w: u32, h: u32) -> u32 {
+        let mut score = 0;
+        if x == 0 || x + w == self.width {
+            score += h;
+        }
+        if y == 0 || y + h == self.height {
+            score += w;
+        }
+        for used in &self.used {
+            if used.x == x + w || used.x + used.w == x {
+                score += common_interval(used.y, used.y + used.h, y, y + h);
+            }
+            if used.y == y + h || used.y + used.h == y {
+                score += common_interval(used.x, used.x + used.w, x, x + w);
+            }
+        }
+        score
+    }
+
+    fn find_position(&self, w: u32, h: u32) -> Option<Placement> {
+        let mut best: Option<((i64, i64), Placement)> = None;
+        for free in &self.free {
+            let mut candidates = vec![(w, h, false)];
+            if self.allow_rotation && w != h {
+                candidates.push((h, w, true));
+            }
+            for (cw, ch, rotated) in candidates {
+                if cw > free.w || ch > free.h {
+                    continue;
+                }
+                let score = self.score(free, cw, ch);
+                if best.as_ref().is_none_or(|(s, _)| score < *s) {
+                    best = Some((
+                        score,
+                        Placement {
+                            x: free.x,
+                            y: free.y,
+                            rotated,
+                        },
+                    ));
+                }
+            }
+        }
+        best.map(|(_, placement)| placement)
+    }
+
+    fn split_free_rects(&mut self, used: &Rect) {
+        let mut new_free = Vec::new();
+        self.free.retain(|free| {
+            if !overlaps(free, used) {
+                return true;
+            }
+            if used.x > free.x {
+                new_free.push(Rect::new(free.x, free.y, used.x - free.x, free.h));
+            }
+            if used.x + used.w < free.x + free.w {
+                new_free.push(Rect::new(
+                    used.x + used.w,
+                    free.y,
+                    free.x + free.w - (used.x + used.w),
+                    free.h,
+                ));
+            }
+            if used.y > free.y {
+                new_free.push(Rect::new(free.x, free.y, free.w, used.y - free.y));
+            }
+            if used.y + used.h < free.y + free.h {
+                new_free.push(Rect::new(
+                    free.x,
+                    used.y + used.h,
+                    free.w,
+                    free.y + free.h - (used.y + used.h),
+                ));
+            }
+            false
+        });
+        self.free.extend(new_free);
+        self.prune_free_rects();
+    }
+
+    /// Removes free rectangles fully contained in other free rectangles.
+    fn prune_free_rects(&mut self) {
+        let mut i = 0;
+        while i < self.free.len() {
+            let mut j = i + 1;
+            let mut removed = false;
+            while j < self.free.len() {
+                if contains(&self.free[j], &self.free[i]) {
+                    self.free.swap_remove(i);
+                    removed = true;
+                    break;
+                }
+                if contains(&self.free[i], &self.free[j]) {
+                    self.free.swap_remove(j);
+                } else {
+                    j += 1;
+                }
+            }
+            if !removed {
+                i += 1;
+            }
+        }
+    }
+}
+
+impl Packer for MaxRectsPacker {
+    fn pack(&mut self, w: u32, h: u32) -> Option<Placement> {
+        let placement = self.find_position(w, h)?;
+        let (w, h) = if placement.rotated { (h, w) } else { (w, h) };
+        let used = Rect::new(placement.x, placement.y, w, h);
+        self.split_free_rects(&used);
+        self.used.push(used);
+        Some(placement)
+    }
+
+    fn can_pack(&self, w: u32, h: u32) -> bool {
+        self.free.iter().any(|free| {
+            (w <= free.w && h <= free.h) || (self.allow_rotation && h <= free.w && w <= free.h)
+        })
+    }
+}
+
+fn overlaps(a: &Rect, b: &Rect) -> bool {
+    a.x < b.x + b.w && b.x < a.x + a.w && a.y < b.y + b.h && b.y < a.y + a.h
+}
+
+fn contains(outer: &Rect, inner: &Rect) -> bool {
+    inner.x >= outer.x
+        && inner.y >= outer.y
+        && inner.x + inner.w <= outer.x + outer.w
+        && inner.y + inner.h <= outer.y + outer.h
+}
+
+fn common_interval(a_start: u32, a_end: u32, b_start: u32, b_end: u32) -> u32 {
+    a_end.min(b_end).saturating_sub(a_start.max(b_start))
+}
@@ -1,176 +1,293 @@
-use std::cmp::max;
+use std::fmt::Display;
 
+use serde::{Deserialize, Serialize};
 use texture_packer::{Rect, TexturePackerConfig};
 
-struct Skyline {
+mod guillotine;
+mod max_rects;
+mod ordering;
+mod shelf;
+mod sizing;
+mod skyline;
+
+pub use guillotine::GuillotinePacker;
+pub use max_rects::{MaxRectsHeuristic, MaxRectsPacker};
+pub use ordering::SortHeuristic;
+pub use shelf::ShelfPacker;
+pub use sizing::{AutoSizeConfig, minimal_size};
+pub use skyline::SkylinePacker;
+
+/// Position of a rectangle reserved by a [`Packer`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Placement {
+    /// Left edge of the rectangle.
     pub x: u32,
+    /// Top edge of the rectangle.
     pub y: u32,
-    pub w: u32,
+    /// Whether the rectangle was rotated by 90 degrees, swapping its width and height.
+    pub rotated: bool,
 }
 
-impl Skyline {
-    #[inline(always)]
-    pub fn left(&self) -> u32 {
-        self.x
-    }
-
-    #[inline(always)]
-    pub fn right(&self) -> u32 {
-        self.x + self.w - 1
-    }
+/// Common interface of the rectangle packing algorithms.
+pub trait Packer {
+    /// Reserves space for a `w`x`h` rectangle, returns `None` when it does not fit.
+    fn pack(&mut self, w: u32, h: u32) -> Option<Placement>;
+    /// Checks if a `w`x`h` rectangle fits without reserving space for it.
+    fn can_pack(&self, w: u32, h: u32) -> bool;
 }
 
-pub struct SkylinePacker {
-    config: TexturePackerConfig,
-    border: Rect,
-
-    // the skylines are sorted by their `x` position
-    skylines: Vec<Skyline>,
+/// Algorithm used for placing images in the atlas.
+#[derive(Clone, Debug, Default, Copy, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum PackingAlgorithm {
+    /// Places images on the lowest edge of the packed area, see [`SkylinePacker`].
+    #[default]
+    Skyline,
+    /// [`MaxRectsPacker`] with [`MaxRectsHeuristic::BestShortSideFit`].
+    MaxRectsBestShortSideFit,
+    /// [`MaxRectsPacker`] with [`MaxRectsHeuristic::BestLongSideFit`].
+    MaxRectsBestLongSideFit,
+    /// [`MaxRectsPacker`] with [`MaxRectsHeuristic::BestAreaFit`].
+    MaxRectsBestAreaFit,
+    /// [`MaxRectsPacker`] with [`MaxRectsHeuristic::BottomLeft`].
+    MaxRectsBottomLeft,
+    /// [`MaxRectsPacker`] with [`MaxRectsHeuristic::ContactPoint`].
+    MaxRectsContactPoint,
+    /// Splits free rectangles in two after every image, see [`GuillotinePacker`].
+    Guillotine,
+    /// Places images in rows, see [`ShelfPacker`].
+    Shelf,
 }
 
-impl SkylinePacker {
-    pub fn new(config: TexturePackerConfig) -> Self {
-        let skylines = vec![Skyline {
-            x: 0,
-            y: 0,
-            w: config.max_width,
-        }];
+impl PackingAlgorithm {
+    /// All algorithms, in the order shown to users.
+    pub const ALL: [PackingAlgorithm; 8] = [
+        PackingAlgorithm::Skyline,
+        PackingAlgorithm::MaxRectsBestShortSideFit,
+        PackingAlgorithm::MaxRectsBestLongSideFit,
+        PackingAlgorithm::MaxRectsBestAreaFit,
+        PackingAlgorithm::MaxRectsBottomLeft,
+        PackingAlgorithm::MaxRectsContactPoint,
+        PackingAlgorithm::Guillotine,
+        PackingAlgorithm::Shelf,
+    ];
 
-        SkylinePacker {
-            config,
-            border: Rect::new(0, 0, config.max_width, config.max_height),
-            skylines,
+    /// Creates an empty packer for a `width`x`height` area.
+    pub fn create_packer(
+        &self,
+        width: u32,
+        height: u32,
+        allow_rotation: bool,
+    ) -> Box<dyn Packer + Send + Sync> {
+        let max_rects = |heuristic| {
+            Box::new(MaxRectsPacker::new(
+                width,
+                height,
+                allow_rotation,
+                heuristic,
+            )) as Box<dyn Packer + Send + Sync>
+        };
+        match self {
+            PackingAlgorithm::Skyline => {
+                Box::new(SkylinePacker::with_size(width, height, allow_rotation))
+            }
+            PackingAlgorithm::MaxRectsBestShortSideFit => {
+                max_rects(MaxRectsHeuristic::BestShortSideFit)
+            }
+            PackingAlgorithm::MaxRectsBestLongSideFit => {
+                max_rects(MaxRectsHeuristic::BestLongSideFit)
+            }
+            PackingAlgorithm::MaxRectsBestAreaFit => max_rects(MaxRectsHeuristic::BestAreaFit),
+            PackingAlgorithm::MaxRectsBottomLeft => max_rects(MaxRectsHeuristic::BottomLeft),
+            PackingAlgorithm::MaxRectsContactPoint => max_rects(MaxRectsHeuristic::ContactPoint),
+            PackingAlgorithm::Guillotine => {
+                Box::new(GuillotinePacker::new(width, height, allow_rotation))
+            }
+            PackingAlgorithm::Shelf => Box::new(ShelfPacker::new(width, height, allow_rotation)),
         }
     }
+}
 
-    /// return `rect` if rectangle (w, h) can fit the skyline started at `i`
-    pub fn can_put(&self, mut i: usize, w: u32, h: u32) -> Option<Rect> {
-        let mut rect = Rect::new(self.skylines[i].x, 0, w, h);
-        let mut width_left = rect.w;
-        loop {
-            rect.y = max(rect.y, self.skylines[i].y);
-            // the source rect is too large
-            if !self.border.contains(&rect) {
-                return None;
-            }
-            if self.skylines[i].w >= width_left {
-                return Some(rect);
-            }
-            width_left -= self.skylines[i].w;
-            i += 1;
-            assert!(i < self.skylines.len());
+impl Display for PackingAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PackingAlgorithm::Skyline => f.write_str("Skyline"),
+            PackingAlgorithm::MaxRectsBestShortSideFit => f.write_str("MaxRects (Best Short Side)"),
+            PackingAlgorithm::MaxRectsBestLongSideFit => f.write_str("MaxRects (Best Long Side)"),
+            PackingAlgorithm::MaxRectsBestAreaFit => f.write_str("MaxRects (Best Area)"),
+            PackingAlgorithm::MaxRectsBottomLeft => f.write_str("MaxRects (Bottom Left)"),
+            PackingAlgorithm::MaxRectsContactPoint => f.write_str("MaxRects (Contact Point)"),
+            PackingAlgorithm::Guillotine => f.write_str("Guillotine"),
+            PackingAlgorithm::Shelf => f.write_str("Shelf"),
         }
     }
+}
 
-    pub fn find_skyline(&self, w: u32, h: u32) -> Option<(usize, Rect)> {
-        let mut bottom = u32::MAX;
-        let mut width = u32::MAX;
-        let mut index = None;
-        let mut rect = Rect::new(0, 0, 0, 0);
-
-        // keep the `bottom` and `width` as small as possible
-        for i in 0..self.skylines.len() {
-            if let Some(r) = self.can_put(i, w, h)
-                && (r.bottom() < bottom || (r.bottom() == bottom && self.skylines[i].w < width))
-            {
-                bottom = r.bottom();
-                width = self.skylines[i].w;
-                index = Some(i);
-                rect = r;
-            }
+/// Packs images into a single atlas page, applying border, padding and extrusion from the config.
+pub struct AtlasPacker {
+    config: TexturePackerConfig,
+    packer: Box<dyn Packer + Send + Sync>,
+}
 
-            if self.config.allow_rotation
-                && let Some(r) = self.can_put(i, h, w)
-                && (r.bottom() < bottom || (r.bottom() == bottom && self.skylines[i].w < width))
-            {
-                bottom = r.bottom();
-                width = self.skylines[i].w;
-                index = Some(i);
-                rect = r;
-            }
+impl AtlasPacker {
+    /// Creates an empty page of the size from `config` packed with `algorithm`.
+    pub fn new(algorithm: PackingAlgorithm, config: TexturePackerConfig) -> Self {
+        // Padding is added to every image, so the area is extended to not require it after the last one
+        let width =
+            config.max_width.saturating_sub(config.border_padding * 2) + config.texture_padding;
+        let height =
+            config.max_height.saturating_sub(config.border_padding * 2) + config.texture_padding;
+        Self {
+            config,
+            packer: algorithm.create_packer(width, height, config.allow_rotation),
         }
+    }
 
-        index.map(|x| (x, rect))
+    fn inflate(&self, w: u32, h: u32) -> (u32, u32) {
+        let extra = self.config.texture_padding + self.config.texture_extrusion * 2;
+        (w + extra, h + extra)
     }
 
-    pub fn split(&mut self, index: usize, rect: &Rect) {
-        let skyline = Skyline {
-            x: rect.left(),
-            y: rect.bottom() + 1,
-            w: rect.w,
-        };
+    /// Checks if a `w`x`h` image fits without reserving space for it.
+    pub fn can_pack(&self, w: u32, h: u32) -> bool {
+        let (w, h) = self.inflate(w, h);
+        self.packer.can_pack(w, h)
+    }
 
-        assert!(skyline.right() <= self.border.right());
-        assert!(skyline.y <= self.border.bottom());
+    /// Packs a `w`x`h` image, returns its area in the atlas without extrusion and whether it was rotated.
+    pub fn pack(&mut self, w: u32, h: u32) -> Option<(Rect, bool)> {
+        let (inflated_w, inflated_h) = self.inflate(w, h);
+        let placement = self.packer.pack(inflated_w, inflated_h)?;
+        let offset = self.config.border_padding + self.config.texture_extrusion;
+        let (w, h) = if placement.rotated { (h, w) } else { (w, h) };
+        Some((
+            Rect::new(placement.x + offset, placement.y + offset, w, h),
+            placement.rotated,
+        ))
+    }
+}
 
-        self.skylines.insert(index, skyline);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let i = index + 1;
-        while i < self.skylines.len() {
-            assert!(self.skylines[i - 1].left() <= self.skylines[i].left());
+    /// Deterministic pseudo-random sizes in `1..=max`.
+    pub(super) fn random_sizes(seed: u64, count: usize, max: u32) -> Vec<(u32, u32)> {
+        let mut state = seed;
+        let mut next = move || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((state >> 33) as u32 % max) + 1
+        };
+        (0..count).map(|_| (next(), next())).collect()
+    }
 
-            if self.skylines[i].left() <= self.skylines[i - 1].right() {
-                let shrink = self.skylines[i - 1].right() - self.skylines[i].left() + 1;
-                if self.skylines[i].w <= shrink {
-                    self.skylines.remove(i);
-                } else {
-                    self.skylines[i].x += shrink;
-                    self.skylines[i].w -= shrink;
-                    break;
-                }
-            } else {
-                break;
-            }
+    fn overlaps(a: &Rect, b: &Rect) -> bool {
+        a.x < b.x + b.w && b.x < a.x + a.w && a.y < b.y + b.h && b.y < a.y + a.h
+    }
+
+    fn config(size: u32, allow_rotation: bool) -> TexturePackerConfig {
+        TexturePackerConfig {
+            max_width: size,
+            max_height: size,
+            allow_rotation,
+            border_padding: 0,
+            texture_padding: 0,
+            texture_extrusion: 0,
+            ..Default::default()
         }
     }
 
-    pub fn merge(&mut self) {
-        let mut i = 1;
-        while i < self.skylines.len() {
-            if self.skylines[i - 1].y == self.skylines[i].y {
-                self.skylines[i - 1].w += self.skylines[i].w;
-                self.skylines.remove(i);
-                i -= 1;
+    #[test]
+    fn packers_place_rects_in_bounds_without_overlaps() {
+        for algorithm in PackingAlgorithm::ALL {
+            for allow_rotation in [false, true] {
+                for seed in 0..8 {
+                    let (width, height) = (256, 192);
+                    let mut packer = algorithm.create_packer(width, height, allow_rotation);
+                    let mut placed: Vec<Rect> = Vec::new();
+                    for (w, h) in random_sizes(seed, 200, 48) {
+                        let can_pack = packer.can_pack(w, h);
+                        let Some(placement) = packer.pack(w, h) else {
+                            assert!(!can_pack, "{algorithm}: can_pack disagrees with pack");
+                            continue;
+                        };
+                        assert!(can_pack, "{algorithm}: can_pack disagrees with pack");
+                        assert!(allow_rotation || !placement.rotated);
+                        let (w, h) = if placement.rotated { (h, w) } else { (w, h) };
+                        let rect = Rect::new(placement.x, placement.y, w, h);
+                        assert!(
+                            rect.x + rect.w <= width && rect.y + rect.h <= height,
+                            "{algorithm}: {rect:?} is out of bounds"
+                        );
+                        if let Some(other) = placed.iter().find(|other| overlaps(other, &rect)) {
+                            panic!("{algorithm}: {rect:?} overlaps {other:?}");
+                        }
+                        placed.push(rect);
+                    }
+                    assert!(!placed.is_empty(), "{algorithm}: nothing was packed");
+                }
             }
-            i += 1;
         }
     }
 
-    pub fn pack(&mut self, texture_rect: &Rect) -> Option<()> {
-        let mut width = texture_rect.w;
-        let mut height = texture_rect.h;
-
-        width += self.config.texture_padding + self.config.texture_extrusion * 2;
-        height += self.config.texture_padding + self.config.texture_extrusion * 2;
-
-        if let Some((i, mut rect)) = self.find_skyline(width, height) {
-            self.split(i, &rect);
-            self.merge();
-
-            // let rotated = width != rect.w;
-
-            rect.w -= self.config.texture_padding + self.config.texture_extrusion * 2;
-            rect.h -= self.config.texture_padding + self.config.texture_extrusion * 2;
-
-            Some(())
-        } else {
-            None
+    #[test]
+    fn atlas_packer_keeps_padding_and_extrusion() {
+        for algorithm in PackingAlgorithm::ALL {
+            for seed in 0..4 {
+                let config = TexturePackerConfig {
+                    border_padding: 3,
+                    texture_padding: 2,
+                    texture_extrusion: 1,
+                    ..config(256, true)
+                };
+                let mut packer = AtlasPacker::new(algorithm, config);
+                let mut placed: Vec<Rect> = Vec::new();
+                for (w, h) in random_sizes(seed, 100, 40) {
+                    let Some((rect, _)) = packer.pack(w, h) else {
+                        continue;
+                    };
+                    let border = config.border_padding + config.texture_extrusion;
+                    assert!(
+                        rect.x >= border
+                            && rect.y >= border
+                            && rect.x + rect.w + border <= config.max_width
+                            && rect.y + rect.h + border <= config.max_height,
+                        "{algorithm}: {rect:?} is inside the border"
+                    );
+                    // Images must stay apart by the padding plus the extrusion of both
+                    let gap = config.texture_padding + config.texture_extrusion * 2;
+                    let grown = Rect::new(rect.x, rect.y, rect.w + gap, rect.h + gap);
+                    for other in placed.iter() {
+                        let other_grown = Rect::new(other.x, other.y, other.w + gap, other.h + gap);
+                        assert!(
+                            !overlaps(&grown, other) && !overlaps(&other_grown, &rect),
+                            "{algorithm}: {rect:?} is too close to {other:?}"
+                        );
+                    }
+                    placed.push(rect);
+                }
+                assert!(!placed.is_empty(), "{algorithm}: nothing was packed");
+            }
         }
     }
 
-    pub fn can_pack(&self, texture_rect: &Rect) -> bool {
-        if let Some((_, rect)) = self.find_skyline(
-            texture_rect.w + self.config.texture_padding + self.config.texture_extrusion * 2,
-            texture_rect.h + self.config.texture_padding + self.config.texture_extrusion * 2,
-        ) {
-            let skyline = Skyline {
-                x: rect.left(),
-                y: rect.bottom() + 1,
-                w: rect.w,
-            };
-
-            return skyline.right() <= self.border.right() && skyline.y <= self.border.bottom();
+    #[test]
+    fn skyline_packer_keeps_config_constructor() {
+        let mut packer = SkylinePacker::new(TexturePackerConfig {
+            texture_padding: 2,
+            ..config(16, false)
+        });
+        assert!(packer.can_pack_rect(&Rect::new(0, 0, 6, 6)));
+        assert!(packer.pack_rect(&Rect::new(0, 0, 6, 6)).is_some());
+        assert!(packer.pack_rect(&Rect::new(0, 0, 6, 6)).is_some());
+        assert!(!packer.can_pack_rect(&Rect::new(0, 0, 8, 16)));
+        #[allow(deprecated)]
+        {
+            assert!(packer.can_pack(&Rect::new(0, 0, 6, 6)));
+            assert!(packer.pack(&Rect::new(0, 0, 6, 6)).is_some());
+            assert!(!packer.can_pack(&Rect::new(0, 0, 8, 16)));
         }
-        false
     }
 }
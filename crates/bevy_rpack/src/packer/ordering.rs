@@ -0,0 +1,70 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// Order in which images are passed to the packer, largest first.
+#[derive(Clone, Debug, Default, Copy, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum SortHeuristic {
+    /// Keeps the input order.
+    #[default]
+    None,
+    /// Largest area first.
+    Area,
+    /// Longest side first.
+    MaxSide,
+    /// Tallest first.
+    Height,
+    /// Widest first.
+    Width,
+    /// Largest sum of width and height first.
+    Perimeter,
+    /// Packs images with every other heuristic and keeps the densest result.
+    Best,
+}
+
+impl SortHeuristic {
+    /// Heuristics tried by [`SortHeuristic::Best`].
+    pub const CANDIDATES: [SortHeuristic; 6] = [
+        SortHeuristic::None,
+        SortHeuristic::Area,
+        SortHeuristic::MaxSide,
+        SortHeuristic::Height,
+        SortHeuristic::Width,
+        SortHeuristic::Perimeter,
+    ];
+
+    /// Returns indices of `sizes` in packing order.
+    ///
+    /// Sorting is stable, `None` and `Best` keep the input order.
+    pub fn order(&self, sizes: &[(u32, u32)]) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..sizes.len()).collect();
+        let key = |(w, h): (u32, u32)| -> (u64, u64) {
+            let (w, h) = (w as u64, h as u64);
+            match self {
+                SortHeuristic::None | SortHeuristic::Best => (0, 0),
+                SortHeuristic::Area => (w * h, w.max(h)),
+                SortHeuristic::MaxSide => (w.max(h), w * h),
+                SortHeuristic::Height => (h, w),
+                SortHeuristic::Width => (w, h),
+                SortHeuristic::Perimeter => (w + h, w * h),
+            }
+        };
+        indices.sort_by_key(|&i| std::cmp::Reverse(key(sizes[i])));
+        indices
+    }
+}
+
+impl Display for SortHeuristic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SortHeuristic::None => f.write_str("None"),
+            SortHeuristic::Area => f.write_str("Area"),
+            SortHeuristic::MaxSide => f.write_str("Max Side"),
+            SortHeuristic::Height => f.write_str("Height"),
+            SortHeuristic::Width => f.write_str("Width"),
+            SortHeuristic::Perimeter => f.write_str("Perimeter"),
+            SortHeuristic::Best => f.write_str("Best"),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use super::{Packer, Placement};
+
+/// Width, height and whether the image is rotated.
+type Orientation = (u32, u32, bool);
+
+struct Shelf {
+    y: u32,
+    height: u32,
+    used_width: u32,
+}
+
+/// Packer placing images in rows, fast but less dense than the other packers.
+pub struct ShelfPacker {
+    width: u32,
+    height: u32,
+    allow_rotation: bool,
+    shelves: Vec<Shelf>,
+}
+
+impl ShelfPacker {
+    /// Creates an empty packer for a `width`x`height` area.
+    pub fn new(width: u32, height: u32, allow_rotation: bool) -> Self {
+        Self {
+            width,
+            height,
+            allow_rotation,
+            shelves: Vec::new(),
+        }
+    }
+
+    fn orientations(&self, w: u32, h: u32) -> Vec<Orientation> {
+        let mut orientations = vec![(w, h, false)];
+        if self.allow_rotation && w != h {
+            orientations.push((h, w, true));
+        }
+        orientations
+    }
+
+    /// Returns index of the shelf and orientation with the smallest height waste, `None` for a new shelf.
+    fn find_position(&self, w: u32, h: u32) -> Option<(Option<usize>, Orientation)> {
+        let mut best: Option<(u32, Option<usize>, Orientation)> = None;
+        for (i, shelf) in self.shelves.iter().enumerate() {
+            for (cw, ch, rotated) in self.orientations(w, h) {
+                if ch > shelf.height || shelf.used_width + cw > self.width {
+                    continue;
+                }
+                let waste = shelf.height - ch;
+                if best.as_ref().is_none_or(|(score, _, _)| waste < *score) {
+                    best = Some((waste, Some(i), (cw, ch, rotated)));
+                }
+            }
+        }
+        if let Some((_, shelf, orientation)) = best {
+            return Some((shelf, orientation));
+        }
+
+        // Open a new shelf as low as possible
+        let y = self.shelves.last().map_or(0, |s| s.y + s.height);
+        self.orientations(w, h)
+            .into_iter()
+            .filter(|(cw, ch, _)| *cw <= self.width && y + ch <= self.height)
+            .min_by_key(|(_, ch, _)| *ch)
+            .map(|orientation| (None, orientation))
+    }
+}
+
+impl Packer for ShelfPacker {
+    fn pack(&mut self, w: u32, h: u32) -> Option<Placement> {
+        let (shelf, (w, h, rotated)) = self.find_position(w, h)?;
+        let index = match shelf {
+            Some(index) => index,
+            None => {
+                let y = self.shelves.last().map_or(0, |s| s.y + s.height);
+                self.shelves.push(Shelf {
+                    y,
+                    height: h,
+                    used_width: 0,
+                });
+                self.shelves.len() - 1
+            }
+        };
+        let shelf = &mut self.shelves[index];
+        let placement = Placement {
+            x: shelf.used_width,
+            y: shelf.y,
+            rotated,
+        };
+        shelf.used_width += w;
+        Some(placement)
+    }
+
+    fn can_pack(&self, w: u32, h: u32) -> bool {
+        self.find_position(w, h).is_some()
+    }
+}
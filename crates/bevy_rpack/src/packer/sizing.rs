@@ -0,0 +1,249 @@
+use texture_packer::TexturePackerConfig;
+
+use super::{AtlasPacker, PackingAlgorithm};
+
+/// Options of the search for the smallest atlas fitting all images.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AutoSizeConfig {
+    /// Limits width and height to powers of two.
+    pub power_of_two: bool,
+    /// Allows width and height to differ.
+    pub allow_non_square: bool,
+}
+
+/// Number of widths checked when looking for a non-square size that is not a power of two.
+const NON_SQUARE_WIDTH_STEPS: u32 = 32;
+
+/// Finds the smallest atlas size that fits all `sizes` on a single page.
+///
+/// `config.max_width` and `config.max_height` are the upper bound of the search,
+/// returns `None` when images do not fit even the maximal size.
+pub fn minimal_size(
+    algorithm: PackingAlgorithm,
+    config: TexturePackerConfig,
+    sizes: &[(u32, u32)],
+    options: AutoSizeConfig,
+) -> Option<(u32, u32)> {
+    let fits = |width: u32, height: u32| {
+        let mut packer = AtlasPacker::new(
+            algorithm,
+            TexturePackerConfig {
+                max_width: width,
+                max_height: height,
+                ..config
+            },
+        );
+        sizes.iter().all(|&(w, h)| packer.pack(w, h).is_some())
+    };
+
+    let max_width = config.max_width;
+    let max_height = config.max_height;
+    // Lower bounds skip padding, the packing area is extended by it
+    let extrusion = config.texture_extrusion * 2;
+    let min_area: u64 = sizes
+        .iter()
+        .map(|&(w, h)| (w + extrusion) as u64 * (h + extrusion) as u64)
+        .sum();
+    // Rotated images only need their shorter side to fit
+    let required_side = |(w, h): (u32, u32)| {
+        if config.allow_rotation {
+            w.min(h)
+        } else {
+            w.max(h)
+        }
+    };
+    let min_side = sizes
+        .iter()
+        .map(|&size| required_side(size) + extrusion + config.border_padding * 2)
+        .max()
+        .unwrap_or(1)
+        .max(1);
+
+    if options.power_of_two {
+        let powers = |max: u32| {
+            (0..u32::BITS)
+                .map(|i| 1u32 << i)
+                .take_while(move |&s| s <= max)
+                .filter(|&s| s >= min_side)
+        };
+        let mut candidates: Vec<(u32, u32)> = if options.allow_non_square {
+            powers(max_width)
+                .flat_map(|w| powers(max_height).map(move |h| (w, h)))
+                .collect()
+        } else {
+            powers(max_width.min(max_height)).map(|s| (s, s)).collect()
+        };
+        // Prefer the smallest area, then the most square shape
+        candidates.sort_by_key(|&(w, h)| (w as u64 * h as u64, w.abs_diff(h)));
+        return candidates
+            .into_iter()
+            .filter(|&(w, h)| w as u64 * h as u64 >= min_area)
+            .find(|&(w, h)| fits(w, h));
+    }
+
+    if !options.allow_non_square {
+        let max = max_width.min(max_height);
+        return smallest_fitting(min_side, max, |s| fits(s, s)).map(|s| (s, s));
+    }
+
+    let step = (max_width.saturating_sub(min_side) / NON_SQUARE_WIDTH_STEPS).max(1);
+    let widths = (min_side..=max_width)
+        .step_by(step as usize)
+        .chain(std::iter::once(max_width));
+    let mut best: Option<(u32, u32)> = None;
+    for width in widths {
+        let min_height = (min_area.div_ceil(width as u64) as u32).max(min_side);
+        if min_height > max_height {
+            continue;
+        }
+        if best.is_some_and(|(w, h)| width as u64 * min_height as u64 >= w as u64 * h as u64) {
+            continue;
+        }
+        let Some(height) = smallest_fitting(min_height, max_height, |h| fits(width, h)) else {
+            continue;
+        };
+        let area = width as u64 * height as u64;
+        if best.is_none_or(|(w, h)| area < w as u64 * h as u64) {
+            best = Some((width, height));
+        }
+    }
+    best
+}
+
+/// Binary search for the smallest value in `min..=max` accepted by `fits`.
+///
+/// Packing is not strictly monotonic, but the returned value is always one that `fits` accepted.
+fn smallest_fitting(min: u32, max: u32, fits: impl Fn(u32) -> bool) -> Option<u32> {
+    if min > max || !fits(max) {
+        return None;
+    }
+    let (mut low, mut high) = (min, max);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if fits(mid) {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    Some(high)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packer::tests::random_sizes;
+
+    fn fits(
+        algorithm: PackingAlgorithm,
+        config: TexturePackerConfig,
+        sizes: &[(u32, u32)],
+        (width, height): (u32, u32),
+    ) -> bool {
+        let mut packer = AtlasPacker::new(
+            algorithm,
+            TexturePackerConfig {
+                max_width: width,
+                max_height: height,
+                ..config
+            },
+        );
+        sizes.iter().all(|&(w, h)| packer.pack(w, h).is_some())
+    }
+
+    #[test]
+    fn minimal_size_fits_all_images() {
+        let options = [
+            AutoSizeConfig {
+                power_of_two: false,
+                allow_non_square: false,
+            },
+            AutoSizeConfig {
+                power_of_two: false,
+                allow_non_square: true,
+            },
+            AutoSizeConfig {
+                power_of_two: true,
+                allow_non_square: false,
+            },
+            AutoSizeConfig {
+                power_of_two: true,
+                allow_non_square: true,
+            },
+        ];
+        let config = TexturePackerConfig {
+            max_width: 1024,
+            max_height: 1024,
+            allow_rotation: false,
+            border_padding: 2,
+            texture_padding: 2,
+            texture_extrusion: 0,
+            ..Default::default()
+        };
+        for algorithm in PackingAlgorithm::ALL {
+            for options in options {
+                let sizes = random_sizes(7, 40, 64);
+                let (width, height) = minimal_size(algorithm, config, &sizes, options)
+                    .unwrap_or_else(|| panic!("{algorithm}: no size found"));
+                assert!(width <= config.max_width && height <= config.max_height);
+                assert!(options.allow_non_square || width == height);
+                assert!(
+                    !options.power_of_two || (width.is_power_of_two() && height.is_power_of_two())
+                );
+                assert!(
+                    fits(algorithm, config, &sizes, (width, height)),
+                    "{algorithm}: images do not fit {width}x{height}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn minimal_size_respects_separate_bounds() {
+        let config = TexturePackerConfig {
+            max_width: 512,
+            max_height: 64,
+            texture_padding: 2,
+            ..Default::default()
+        };
+        let sizes = vec![(30, 30); 24];
+        for power_of_two in [false, true] {
+            let options = AutoSizeConfig {
+                power_of_two,
+                allow_non_square: true,
+            };
+            let (width, height) =
+                minimal_size(PackingAlgorithm::Skyline, config, &sizes, options).unwrap();
+            assert!(width <= 512 && height <= 64, "{width}x{height}");
+            assert!(width > height);
+            assert!(fits(
+                PackingAlgorithm::Skyline,
+                config,
+                &sizes,
+                (width, height)
+            ));
+        }
+        let options = AutoSizeConfig {
+            power_of_two: false,
+            allow_non_square: false,
+        };
+        assert_eq!(
+            minimal_size(PackingAlgorithm::Skyline, config, &sizes, options),
+            None
+        );
+    }
+
+    #[test]
+    fn minimal_size_rejects_too_large_images() {
+        let config = TexturePackerConfig {
+            max_width: 64,
+            max_height: 64,
+            ..Default::default()
+        };
+        let options = AutoSizeConfig::default();
+        assert_eq!(
+            minimal_size(PackingAlgorithm::Skyline, config, &[(65, 10)], options),
+            None
+        );
+    }
+}
@@ -2,6 +2,8 @@ use std::cmp::max;
 
 use texture_packer::{Rect, TexturePackerConfig};
 
+use super::{Packer, Placement};
+
 struct Skyline {
     pub x: u32,
     pub y: u32,
@@ -20,6 +22,7 @@ impl Skyline {
     }
 }
 
+/// Skyline rectangle packer shared by runtime atlas generation and `rpack_cli`.
 pub struct SkylinePacker {
     config: TexturePackerConfig,
     border: Rect,
@@ -29,6 +32,9 @@ pub struct SkylinePacker {
 }
 
 impl SkylinePacker {
+    /// Creates a packer for the `max_width`x`max_height` area of the `config`.
+    ///
+    /// Rectangles passed to [`SkylinePacker::pack_rect`] are extended by the padding and extrusion of the `config`.
     pub fn new(config: TexturePackerConfig) -> Self {
         let skylines = vec![Skyline {
             x: 0,
@@ -43,7 +49,20 @@ impl SkylinePacker {
         }
     }
 
-    // return `rect` if rectangle (w, h) can fit the skyline started at `i`
+    /// Creates a packer for a `width`x`height` area without padding or extrusion.
+    pub fn with_size(width: u32, height: u32, allow_rotation: bool) -> Self {
+        Self::new(TexturePackerConfig {
+            max_width: width,
+            max_height: height,
+            allow_rotation,
+            border_padding: 0,
+            texture_padding: 0,
+            texture_extrusion: 0,
+            ..Default::default()
+        })
+    }
+
+    /// Returns `rect` if rectangle (w, h) can fit the skyline started at `i`.
     pub fn can_put(&self, mut i: usize, w: u32, h: u32) -> Option<Rect> {
         let mut rect = Rect::new(self.skylines[i].x, 0, w, h);
         let mut width_left = rect.w;
@@ -62,6 +81,7 @@ impl SkylinePacker {
         }
     }
 
+    /// Returns the skyline index and the area with the lowest bottom where a (w, h) rectangle fits.
     pub fn find_skyline(&self, w: u32, h: u32) -> Option<(usize, Rect)> {
         let mut bottom = u32::MAX;
         let mut width = u32::MAX;
@@ -93,6 +113,7 @@ impl SkylinePacker {
         index.map(|x| (x, rect))
     }
 
+    /// Raises the skyline at `index` to the bottom of the placed `rect`.
     pub fn split(&mut self, index: usize, rect: &Rect) {
         let skyline = Skyline {
             x: rect.left(),
@@ -101,7 +122,8 @@ impl SkylinePacker {
         };
 
         assert!(skyline.right() <= self.border.right());
-        assert!(skyline.y <= self.border.bottom());
+        // A rectangle ending flush with the bottom edge leaves the skyline just below the area
+        assert!(skyline.y <= self.border.bottom() + 1);
 
         self.skylines.insert(index, skyline);
 
@@ -124,6 +146,7 @@ impl SkylinePacker {
         }
     }
 
+    /// Joins neighbouring skylines at the same height.
     pub fn merge(&mut self) {
         let mut i = 1;
         while i < self.skylines.len() {
@@ -136,41 +159,61 @@ impl SkylinePacker {
         }
     }
 
-    pub fn pack(&mut self, texture_rect: &Rect) -> Option<()> {
-        let mut width = texture_rect.w;
-        let mut height = texture_rect.h;
-
-        width += self.config.texture_padding + self.config.texture_extrusion * 2;
-        height += self.config.texture_padding + self.config.texture_extrusion * 2;
+    /// Places the rectangle and returns its position, the size does not include the padding.
+    pub fn pack_rect(&mut self, texture_rect: &Rect) -> Option<Rect> {
+        let (width, height) = self.inflate(texture_rect);
 
         if let Some((i, mut rect)) = self.find_skyline(width, height) {
             self.split(i, &rect);
             self.merge();
 
-            // let rotated = width != rect.w;
-
             rect.w -= self.config.texture_padding + self.config.texture_extrusion * 2;
             rect.h -= self.config.texture_padding + self.config.texture_extrusion * 2;
 
-            Some(())
+            Some(rect)
         } else {
             None
         }
     }
 
+    /// Checks if the rectangle fits without reserving space for it.
+    pub fn can_pack_rect(&self, texture_rect: &Rect) -> bool {
+        let (width, height) = self.inflate(texture_rect);
+        self.find_skyline(width, height).is_some()
+    }
+
+    /// Places the rectangle, returns `None` when it does not fit.
+    #[deprecated(note = "use `SkylinePacker::pack_rect` returning the placed area instead")]
+    pub fn pack(&mut self, texture_rect: &Rect) -> Option<()> {
+        self.pack_rect(texture_rect).map(|_| ())
+    }
+
+    /// Checks if the rectangle fits without reserving space for it.
+    #[deprecated(note = "use `SkylinePacker::can_pack_rect` instead")]
     pub fn can_pack(&self, texture_rect: &Rect) -> bool {
-        if let Some((_, rect)) = self.find_skyline(
-            texture_rect.w + self.config.texture_padding + self.config.texture_extrusion * 2,
-            texture_rect.h + self.config.texture_padding + self.config.texture_extrusion * 2,
-        ) {
-            let skyline = Skyline {
-                x: rect.left(),
-                y: rect.bottom() + 1,
-                w: rect.w,
-            };
-
-            return skyline.right() <= self.border.right() && skyline.y <= self.border.bottom();
-        }
-        false
+        self.can_pack_rect(texture_rect)
+    }
+
+    fn inflate(&self, texture_rect: &Rect) -> (u32, u32) {
+        let extra = self.config.texture_padding + self.config.texture_extrusion * 2;
+        (texture_rect.w + extra, texture_rect.h + extra)
+    }
+}
+
+impl Packer for SkylinePacker {
+    fn pack(&mut self, w: u32, h: u32) -> Option<Placement> {
+        let (i, rect) = self.find_skyline(w, h)?;
+        self.split(i, &rect);
+        self.merge();
+
+        Some(Placement {
+            x: rect.x,
+            y: rect.y,
+            rotated: w != h && rect.w != w,
+        })
+    }
+
+    fn can_pack(&self, w: u32, h: u32) -> bool {
+        self.find_skyline(w, h).is_some()
     }
 }
@@ -1,15 +1,28 @@
-use crate::{AtlasAsset, SerializableRect};
-use bevy_app::{App, Plugin};
-use bevy_asset::{Asset, AssetApp, Assets, Handle, ReflectAsset};
-use bevy_asset::{AssetLoader, AsyncReadExt};
+use crate::generation::{AtlasGenConfig, GenerationError, frame_keys};
+use crate::index::{RpackAtlasKeyIndex, update_rpack_atlas_key_index};
+use crate::processor::{RpackAtlasAssetSaver, RpackAtlasGenProcessor};
+use crate::spritesheet::{ImageFile, Spritesheet, SpritesheetBuildConfig};
+use crate::{AtlasAnimation, AtlasAsset, AtlasFrame, SerializableRect};
+use bevy_app::{App, Plugin, PostUpdate, Update};
+use bevy_asset::io::{AssetReaderError, AssetSourceId, ErasedAssetReader, VecReader};
+use bevy_asset::transformer::IdentityAssetTransformer;
+use bevy_asset::{Asset, AssetApp, AssetEventSystems, Assets, Handle, ReflectAsset};
+use bevy_asset::{
+    AssetLoader, AssetPath, AssetServer, AssetServerMode, AsyncReadExt, ReadAssetBytesError,
+    RenderAssetUsages,
+};
 use bevy_derive::{Deref, DerefMut};
+use bevy_ecs::schedule::IntoScheduleConfigs;
 use bevy_ecs::system::{Res, SystemParam};
-use bevy_image::{Image, ImageSampler, TextureAtlas, TextureAtlasLayout};
+use bevy_image::{Image, ImageFormat, ImageSampler, TextureAtlas, TextureAtlasLayout};
 use bevy_math::{URect, UVec2};
 use bevy_platform::collections::HashMap;
 use bevy_reflect::{Reflect, TypePath};
-use bevy_sprite::Sprite;
-use bevy_ui::widget::ImageNode;
+use bevy_sprite::{Anchor, BorderRect, Sprite, SpriteImageMode, TextureSlicer};
+use bevy_tasks::futures_lite::StreamExt;
+use bevy_ui::widget::{ImageNode, NodeImageMode};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 /// Errors that can occur while accessing and creating components from [`RpackAtlasAsset`].
@@ -21,6 +34,9 @@ pub enum RpackAtlasError {
     /// An error that occured because atlas does not contain provided key.
     #[error("There is no frame with provided key.")]
     WrongKey,
+    /// An error that occured because more than one atlas contains provided key.
+    #[error("Frame key {0} is found in more than one atlas.")]
+    AmbiguousKey(String),
 }
 
 /// This is an asset containing the texture atlas image, the texture atlas layout, and a map of the original file names to their corresponding indices in the texture atlas.
@@ -33,6 +49,23 @@ pub struct RpackAtlasAsset {
     pub atlas: Handle<TextureAtlasLayout>,
     /// The map of the original file names to indices of the texture atlas.
     pub files: HashMap<String, usize>,
+    /// The map of the original file names to their frame data.
+    ///
+    /// Frames rotated in the atlas file are copied to an additional page in their original orientation on load.
+    pub frames: HashMap<String, AtlasFrame>,
+    /// All pages of the atlas, the first one uses the same handles as `image` and `atlas`.
+    pub pages: Vec<RpackAtlasPage>,
+    /// The map of animation names to their frame sequences.
+    pub animations: HashMap<String, AtlasAnimation>,
+}
+
+/// Image and layout of a single page of the [`RpackAtlasAsset`].
+#[derive(Debug, Clone, Reflect)]
+pub struct RpackAtlasPage {
+    /// The texture atlas image of the page.
+    pub image: Handle<Image>,
+    /// The texture atlas layout of the page.
+    pub atlas: Handle<TextureAtlasLayout>,
 }
 
 impl From<SerializableRect> for URect {
@@ -48,8 +81,80 @@ impl From<SerializableRect> for URect {
 }
 
 /// SystemParam helper for accessing and creating components from `Rpack` atlas data.
+///
+/// Keys are looked up in the [`RpackAtlasKeyIndex`], they can be qualified with the atlas name like `tilemap/agents/ship`.
 #[derive(SystemParam, DerefMut, Deref)]
-pub struct RpackAtlases<'w>(pub Res<'w, Assets<RpackAtlasAsset>>);
+pub struct RpackAtlases<'w>(
+    #[deref] pub Res<'w, Assets<RpackAtlasAsset>>,
+    pub Res<'w, RpackAtlasKeyIndex>,
+);
+
+impl RpackAtlases<'_> {
+    /// Retrieves the atlas with the given name, which is its asset path without extensions.
+    pub fn get_atlas<T: AsRef<str>>(&self, name: T) -> Option<&RpackAtlasAsset> {
+        self.0.get(self.1.get_atlas(name.as_ref())?)
+    }
+
+    /// Returns the atlas containing the `key` and the key of the frame within that atlas.
+    ///
+    /// Atlases added in the current frame are not indexed yet, so they are scanned when the key is not found.
+    fn resolve<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> Result<(&'a RpackAtlasAsset, &'a str), RpackAtlasError> {
+        let error = match self.1.resolve(key) {
+            Ok((id, key)) => match self.0.get(id) {
+                Some(atlas) => return Ok((atlas, key)),
+                None => RpackAtlasError::WrongKey,
+            },
+            Err(RpackAtlasError::AmbiguousKey(key)) => {
+                return Err(RpackAtlasError::AmbiguousKey(key));
+            }
+            Err(error) => error,
+        };
+        self.0
+            .iter()
+            .find(|(id, atlas)| !self.1.contains_atlas(*id) && atlas.files.contains_key(key))
+            .map(|(_, atlas)| (atlas, key))
+            .ok_or(if self.0.is_empty() {
+                RpackAtlasError::NoAtlas
+            } else {
+                error
+            })
+    }
+}
+
+impl RpackAssetHelper for RpackAtlases<'_> {
+    fn find_atlas_data_by_key<T: AsRef<str>>(
+        &self,
+        key: T,
+    ) -> Result<(TextureAtlas, Handle<Image>), RpackAtlasError> {
+        let (atlas, key) = self.resolve(key.as_ref())?;
+        atlas.get_atlas_data(key)
+    }
+
+    fn try_make_sprite<T: AsRef<str>>(&self, key: T) -> Result<Sprite, RpackAtlasError> {
+        let (atlas, key) = self.resolve(key.as_ref())?;
+        atlas.try_make_sprite(key)
+    }
+
+    fn try_make_anchored_sprite<T: AsRef<str>>(
+        &self,
+        key: T,
+    ) -> Result<(Sprite, Anchor), RpackAtlasError> {
+        let (atlas, key) = self.resolve(key.as_ref())?;
+        atlas.try_make_anchored_sprite(key)
+    }
+
+    fn try_make_image_node<T: AsRef<str>>(&self, key: T) -> Result<ImageNode, RpackAtlasError> {
+        let (atlas, key) = self.resolve(key.as_ref())?;
+        atlas.try_make_image_node(key)
+    }
+
+    fn atlas_data_keys(&self) -> Vec<&str> {
+        self.1.keys().collect()
+    }
+}
 
 /// A helper trait for accessing and creating components from `Rpack` atlas data.
 #[allow(dead_code)]
@@ -61,6 +166,11 @@ pub trait RpackAssetHelper {
     ) -> Result<(TextureAtlas, Handle<Image>), RpackAtlasError>;
     /// Creates a [`Sprite`] component for the given atlas key, if available in any of the loaded Atlases.
     fn try_make_sprite<T: AsRef<str>>(&self, key: T) -> Result<Sprite, RpackAtlasError>;
+    /// Creates a [`Sprite`] component with its [`Anchor`] for the given atlas key, if available in any of the loaded Atlases.
+    fn try_make_anchored_sprite<T: AsRef<str>>(
+        &self,
+        key: T,
+    ) -> Result<(Sprite, Anchor), RpackAtlasError>;
     /// Creates a [`ImageNode`] component for the given atlas key, if available in any of the loaded Atlases.
     fn try_make_image_node<T: AsRef<str>>(&self, key: T) -> Result<ImageNode, RpackAtlasError>;
 
@@ -68,6 +178,7 @@ pub trait RpackAssetHelper {
     fn atlas_data_keys(&self) -> Vec<&str>;
 }
 
+/// Scans all loaded atlases and uses the first one containing the key, [`RpackAtlases`] uses the [`RpackAtlasKeyIndex`] instead.
 impl RpackAssetHelper for Assets<RpackAtlasAsset> {
     fn atlas_data_keys(&self) -> Vec<&str> {
         self.iter()
@@ -101,6 +212,21 @@ impl RpackAssetHelper for Assets<RpackAtlasAsset> {
         Err(RpackAtlasError::WrongKey)
     }
 
+    fn try_make_anchored_sprite<T: AsRef<str>>(
+        &self,
+        key: T,
+    ) -> Result<(Sprite, Anchor), RpackAtlasError> {
+        if self.is_empty() {
+            return Err(RpackAtlasError::NoAtlas);
+        }
+        for (_, a) in self.iter() {
+            if let Ok(sprite) = a.try_make_anchored_sprite(key.as_ref()) {
+                return Ok(sprite);
+            }
+        }
+        Err(RpackAtlasError::WrongKey)
+    }
+
     fn try_make_image_node<T: AsRef<str>>(&self, key: T) -> Result<ImageNode, RpackAtlasError> {
         if self.is_empty() {
             return Err(RpackAtlasError::NoAtlas);
@@ -120,35 +246,94 @@ impl RpackAtlasAsset {
         &self,
         key: T,
     ) -> Result<(TextureAtlas, Handle<Image>), RpackAtlasError> {
-        match self.files.get(key.as_ref()) {
-            Some(s) => Ok((
-                TextureAtlas {
-                    index: *s,
-                    layout: self.atlas.clone(),
-                },
-                self.image.clone(),
-            )),
-            _ => Err(RpackAtlasError::WrongKey),
+        let key = key.as_ref();
+        let Some(index) = self.files.get(key) else {
+            return Err(RpackAtlasError::WrongKey);
+        };
+        let page = self.frames.get(key).map_or(0, |f| f.page);
+        let (layout, image) = match self.pages.get(page) {
+            Some(p) => (p.atlas.clone(), p.image.clone()),
+            None => (self.atlas.clone(), self.image.clone()),
+        };
+        Ok((
+            TextureAtlas {
+                index: *index,
+                layout,
+            },
+            image,
+        ))
+    }
+
+    /// Retrieves the [`Anchor`] for the given atlas key, placing the frame pivot at the sprite position.
+    ///
+    /// For trimmed frames the anchor is moved so the sprite is placed like the original, untrimmed image.
+    pub fn get_anchor<T: AsRef<str>>(&self, key: T) -> Result<Anchor, RpackAtlasError> {
+        match self.frames.get(key.as_ref()) {
+            Some(frame) => Ok(Anchor(frame.anchor().into())),
+            None => Err(RpackAtlasError::WrongKey),
         }
     }
 
     /// Creates a [`Sprite`] component for the given atlas key
+    ///
+    /// Frames with nine-slice borders use [`SpriteImageMode::Sliced`].
     pub fn try_make_sprite<T: AsRef<str>>(&self, key: T) -> Result<Sprite, RpackAtlasError> {
+        let key = key.as_ref();
         if let Ok((atlas, image)) = self.get_atlas_data(key) {
-            Ok(Sprite::from_atlas_image(image, atlas))
+            let mut sprite = Sprite::from_atlas_image(image, atlas);
+            if let Some(slicer) = self.get_slicer(key) {
+                sprite.image_mode = SpriteImageMode::Sliced(slicer);
+            }
+            Ok(sprite)
         } else {
             Err(RpackAtlasError::WrongKey)
         }
     }
 
+    /// Creates a [`Sprite`] component with its [`Anchor`] for the given atlas key,
+    /// the anchor keeps the pivot of trimmed frames in place.
+    pub fn try_make_anchored_sprite<T: AsRef<str>>(
+        &self,
+        key: T,
+    ) -> Result<(Sprite, Anchor), RpackAtlasError> {
+        let key = key.as_ref();
+        Ok((self.try_make_sprite(key)?, self.get_anchor(key)?))
+    }
+
     /// Creates a [`ImageNode`] component for the given atlas key, if available in any of the loaded Atlases.
+    ///
+    /// Frames with nine-slice borders use [`NodeImageMode::Sliced`].
     pub fn try_make_image_node<T: AsRef<str>>(&self, key: T) -> Result<ImageNode, RpackAtlasError> {
+        let key = key.as_ref();
         if let Ok((atlas, image)) = self.get_atlas_data(key) {
-            Ok(ImageNode::from_atlas_image(image, atlas))
+            let mut image_node = ImageNode::from_atlas_image(image, atlas);
+            if let Some(slicer) = self.get_slicer(key) {
+                image_node.image_mode = NodeImageMode::Sliced(slicer);
+            }
+            Ok(image_node)
         } else {
             Err(RpackAtlasError::WrongKey)
         }
     }
+
+    /// Retrieves the animation with the given name.
+    pub fn get_animation<T: AsRef<str>>(&self, name: T) -> Option<&AtlasAnimation> {
+        self.animations.get(name.as_ref())
+    }
+
+    /// Returns the [`TextureSlicer`] for frames with nine-slice borders.
+    pub fn get_slicer<T: AsRef<str>>(&self, key: T) -> Option<TextureSlicer> {
+        let slice = self.frames.get(key.as_ref())?.slice?;
+        Some(TextureSlicer {
+            border: BorderRect::from([
+                slice.left as f32,
+                slice.right as f32,
+                slice.top as f32,
+                slice.bottom as f32,
+            ]),
+            ..Default::default()
+        })
+    }
 }
 
 /// Plugin that provides support for rpack atlases.
@@ -169,7 +354,28 @@ impl Plugin for RpackAssetPlugin {
         app.register_type::<super::AtlasAsset>();
         app.register_type::<RpackAtlasAsset>();
         app.init_asset::<RpackAtlasAsset>();
+        app.init_asset::<super::AtlasAsset>();
         app.init_asset_loader::<RpackAtlasAssetLoader>();
+        app.init_resource::<RpackAtlasKeyIndex>();
+        app.add_systems(
+            PostUpdate,
+            update_rpack_atlas_key_index.after(AssetEventSystems),
+        );
+        app.add_systems(Update, crate::dynamic::pack_dynamic_atlas_images);
+    }
+
+    fn finish(&self, app: &mut App) {
+        if let Some(asset_server) = app.world().get_resource::<AssetServer>() {
+            let loader = RpackAtlasGenConfigLoader {
+                asset_server: asset_server.clone(),
+            };
+            app.register_asset_loader(loader);
+        }
+        app.register_asset_processor(RpackAtlasGenProcessor::new(
+            IdentityAssetTransformer::new(),
+            RpackAtlasAssetSaver,
+        ));
+        app.set_default_asset_processor::<RpackAtlasGenProcessor>("rpack_gen.json");
     }
 }
 
@@ -193,6 +399,27 @@ pub enum RpackAtlasAssetError {
     /// an atlas.
     #[error("missing image asset: {0}")]
     LoadingImageAsset(String),
+    /// An error that can occur when the atlas contains
+    /// rotated frames and its image format cannot be
+    /// converted to restore their original orientation.
+    #[error("could not restore rotated frames: {0}")]
+    RotatedFrames(String),
+    /// An error that occurred while importing an atlas
+    /// created by another tool.
+    #[error("could not import atlas: {0}")]
+    Import(#[from] crate::import::ImportError),
+    /// An error that occurred while listing or reading
+    /// the source images of a `.rpack_gen.json` file.
+    #[error("could not read source images: {0}")]
+    SourceImages(String),
+    /// An error that occurred while packing the source
+    /// images of a `.rpack_gen.json` file.
+    #[error("could not generate atlas: {0}")]
+    Generation(#[from] GenerationError),
+    /// An error that occurred while saving
+    /// an atlas packed by the asset processor.
+    #[error("could not process atlas: {0}")]
+    ProcessedAtlas(String),
 }
 
 impl From<bevy_asset::LoadDirectError> for RpackAtlasAssetError {
@@ -221,7 +448,15 @@ impl Default for RpackAtlasAssetLoaderSettings {
     }
 }
 
+#[cfg(not(feature = "third_party_formats"))]
+const LOADER_EXTENSIONS: &[&str] = &["rpack.json"];
+#[cfg(feature = "third_party_formats")]
+const LOADER_EXTENSIONS: &[&str] = &["rpack.json", "atlas", "tp.json", "tp.array.json"];
+
 /// The loader responsible for loading `RpackAtlasAsset` files from `.rpack.json` files.
+///
+/// With the `third_party_formats` feature it also loads libGDX `.atlas` and TexturePacker `.tp.json`
+/// and `.tp.array.json` files, as written by the `rpack_cli` exporters.
 #[derive(Default, TypePath)]
 pub struct RpackAtlasAssetLoader;
 
@@ -231,7 +466,7 @@ impl AssetLoader for RpackAtlasAssetLoader {
     type Error = RpackAtlasAssetError;
 
     fn extensions(&self) -> &[&str] {
-        &["rpack.json"]
+        LOADER_EXTENSIONS
     }
 
     async fn load(
@@ -242,43 +477,522 @@ impl AssetLoader for RpackAtlasAssetLoader {
     ) -> Result<Self::Asset, Self::Error> {
         let mut file = String::new();
         reader.read_to_string(&mut file).await?;
-        let asset: AtlasAsset = serde_json::from_str(&file)?;
+        let path = load_context.path().path().to_string_lossy().into_owned();
+        let asset: AtlasAsset = if path.ends_with(".atlas")
+            || path.ends_with(".tp.json")
+            || path.ends_with(".tp.array.json")
+        {
+            crate::import::from_file_content(&path, &file)?
+        } else {
+            serde_json::from_str(&file)?
+        };
 
-        let path = load_context
+        let dir = load_context
             .path()
             .path()
             .parent()
             .unwrap_or(std::path::Path::new(""))
-            .join(asset.filename);
-
-        let mut image: Image = load_context
-            .load_builder()
-            .load_untyped_value(path)
-            .await?
-            .take()
-            .ok_or(RpackAtlasAssetError::LoadingImageAsset(
-                "failed to load image asset, does it exist".to_string(),
-            ))?;
-        image.sampler = settings.image_sampler.clone();
-
-        let mut layout = TextureAtlasLayout::new_empty(UVec2::new(asset.size[0], asset.size[1]));
-        let mut files = HashMap::new();
+            .to_path_buf();
 
-        for frame in asset.frames {
-            let id = layout.add_texture(frame.frame.into());
-            files.insert(frame.key, id);
+        let mut pages = Vec::new();
+        let mut layouts = Vec::new();
+        for (index, page) in asset.pages().into_iter().enumerate() {
+            let loaded = if let Some(data) = page.filename.strip_prefix("data:") {
+                // Pages embedded by the `RpackAtlasAssetSaver` are loaded under a path made from the atlas path,
+                // so the image loader is picked by the extension of their format
+                let (extension, bytes) = decode_data_uri(data)?;
+                let (label, _) = page_labels(index);
+                let path = load_context
+                    .path()
+                    .path()
+                    .with_extension(format!("{label}.{extension}"));
+                load_context
+                    .load_builder()
+                    .load_untyped_value_from_reader(path, &mut VecReader::new(bytes))
+                    .await?
+            } else {
+                load_context
+                    .load_builder()
+                    .load_untyped_value(dir.join(&page.filename))
+                    .await?
+            };
+            let mut image: Image = loaded
+                .take()
+                .ok_or(RpackAtlasAssetError::LoadingImageAsset(format!(
+                    "failed to load image asset {}, does it exist",
+                    page_description(&page.filename)
+                )))?;
+            image.sampler = settings.image_sampler.clone();
+            pages.push(image);
+            layouts.push(TextureAtlasLayout::new_empty(UVec2::new(
+                page.size[0],
+                page.size[1],
+            )));
         }
 
-        let atlas = load_context.add_labeled_asset("atlas_layout", layout);
-        let image = load_context.add_labeled_asset("atlas_texture", image);
+        create_atlas_asset(asset, pages, layouts, settings, load_context)
+    }
+}
 
-        Ok(RpackAtlasAsset {
-            image,
-            atlas,
-            files,
+/// Returns the file extension of the image format and the bytes of the image embedded in a data URI,
+/// `data` is the URI without the `data:` scheme.
+fn decode_data_uri(data: &str) -> Result<(&'static str, Vec<u8>), RpackAtlasAssetError> {
+    use base64::Engine;
+
+    let invalid = |reason: &str| {
+        RpackAtlasAssetError::LoadingImageAsset(format!("{reason} in page data URI"))
+    };
+    let (media_type, payload) = data
+        .split_once(";base64,")
+        .ok_or_else(|| invalid("missing base64 payload"))?;
+    let extension = ImageFormat::from_mime_type(media_type)
+        .and_then(|format| format.to_file_extensions().first().copied())
+        .ok_or_else(|| invalid(&format!("unsupported image type {media_type}")))?;
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(payload)
+        .map_err(|e| invalid(&e.to_string()))?;
+    Ok((extension, bytes))
+}
+
+/// Returns the page filename for error messages, without the payload of data URIs.
+fn page_description(filename: &str) -> &str {
+    filename
+        .split_once(";base64,")
+        .map_or(filename, |(prefix, _)| prefix)
+}
+
+/// Returns the labels of the image and layout assets of the page at `index`.
+pub(crate) fn page_labels(index: usize) -> (String, String) {
+    if index == 0 {
+        ("atlas_texture".to_string(), "atlas_layout".to_string())
+    } else {
+        (
+            format!("atlas_texture_{index}"),
+            format!("atlas_layout_{index}"),
+        )
+    }
+}
+
+/// Creates the [`RpackAtlasAsset`] from the atlas description and its page images and layouts,
+/// adding them as labeled assets.
+pub(crate) fn create_atlas_asset(
+    asset: AtlasAsset,
+    mut pages: Vec<Image>,
+    mut layouts: Vec<TextureAtlasLayout>,
+    settings: &RpackAtlasAssetLoaderSettings,
+    load_context: &mut bevy_asset::LoadContext<'_>,
+) -> Result<RpackAtlasAsset, RpackAtlasAssetError> {
+    let mut asset_frames = asset.frames;
+    if asset_frames.iter().any(|f| f.rotated) {
+        let mut image = unrotate_frames(&mut asset_frames, &pages)?;
+        image.sampler = settings.image_sampler.clone();
+        layouts.push(TextureAtlasLayout::new_empty(image.size()));
+        pages.push(image);
+    }
+
+    let mut files = HashMap::new();
+    let mut frames = HashMap::new();
+    // Aliased frames share the rectangle and get the same index
+    let mut indices = HashMap::new();
+
+    for frame in asset_frames {
+        let Some(layout) = layouts.get_mut(frame.page) else {
+            return Err(RpackAtlasAssetError::LoadingImageAsset(format!(
+                "frame {} references missing page {}",
+                frame.key, frame.page
+            )));
+        };
+        let id = *indices
+            .entry((frame.page, frame.frame))
+            .or_insert_with(|| layout.add_texture(frame.frame.into()));
+        files.insert(frame.key.clone(), id);
+        frames.insert(frame.key.clone(), frame);
+    }
+
+    let pages: Vec<RpackAtlasPage> = pages
+        .into_iter()
+        .zip(layouts)
+        .enumerate()
+        .map(|(i, (image, layout))| {
+            let (image_label, layout_label) = page_labels(i);
+            RpackAtlasPage {
+                atlas: load_context.add_labeled_asset(layout_label, layout),
+                image: load_context.add_labeled_asset(image_label, image),
+            }
         })
+        .collect();
+    let atlas = pages[0].atlas.clone();
+    let image = pages[0].image.clone();
+
+    Ok(RpackAtlasAsset {
+        image,
+        atlas,
+        files,
+        frames,
+        pages,
+        animations: asset.animations.into_iter().collect(),
+    })
+}
+
+/// Space left between frames copied by [`unrotate_frames`].
+const UNROTATED_FRAME_PADDING: u32 = 1;
+
+/// Copies rotated frames to a new page in their original orientation,
+/// so they can be used by [`Sprite`] and [`ImageNode`] like any other frame.
+///
+/// Frames are updated to point at the new page, which is placed after all existing pages.
+fn unrotate_frames(
+    frames: &mut [AtlasFrame],
+    pages: &[Image],
+) -> Result<Image, RpackAtlasAssetError> {
+    let sources = pages
+        .iter()
+        .map(|page| page.clone().try_into_dynamic())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| RpackAtlasAssetError::RotatedFrames(e.to_string()))?;
+
+    let total_area: u32 = frames
+        .iter()
+        .filter(|f| f.rotated)
+        .map(|f| (f.frame.w + UNROTATED_FRAME_PADDING) * (f.frame.h + UNROTATED_FRAME_PADDING))
+        .sum();
+    let max_width = frames
+        .iter()
+        .filter(|f| f.rotated)
+        .map(|f| f.frame.h)
+        .max()
+        .unwrap_or_default();
+    let width = max_width.max(total_area.isqrt());
+
+    // Frames are placed in rows from left to right
+    let (mut x, mut y, mut row_height) = (0, 0, 0);
+    let mut placements = Vec::new();
+    let mut placed = HashMap::new();
+    for (i, frame) in frames.iter().enumerate().filter(|(_, f)| f.rotated) {
+        // Aliased frames are copied once
+        if let Some(&(x, y)) = placed.get(&(frame.page, frame.frame)) {
+            placements.push((i, x, y));
+            continue;
+        }
+        let (w, h) = (frame.frame.h, frame.frame.w);
+        if x + w > width {
+            x = 0;
+            y += row_height + UNROTATED_FRAME_PADDING;
+            row_height = 0;
+        }
+        placements.push((i, x, y));
+        placed.insert((frame.page, frame.frame), (x, y));
+        x += w + UNROTATED_FRAME_PADDING;
+        row_height = row_height.max(h);
     }
+    let height = y + row_height;
+
+    let mut output = image::RgbaImage::new(width, height);
+    let page = pages.len();
+    for (i, x, y) in placements {
+        let frame = &mut frames[i];
+        let Some(source) = sources.get(frame.page) else {
+            return Err(RpackAtlasAssetError::LoadingImageAsset(format!(
+                "frame {} references missing page {}",
+                frame.key, frame.page
+            )));
+        };
+        let rect = frame.frame;
+        let cropped = source.crop_imm(rect.x, rect.y, rect.w, rect.h);
+        let restored = if frame.counter_clockwise {
+            cropped.rotate90()
+        } else {
+            cropped.rotate270()
+        };
+        image::imageops::replace(&mut output, &restored.to_rgba8(), x as i64, y as i64);
+        frame.frame = SerializableRect {
+            x,
+            y,
+            w: rect.h,
+            h: rect.w,
+        };
+        frame.page = page;
+        frame.rotated = false;
+        frame.counter_clockwise = false;
+    }
+
+    Ok(Image::from_dynamic(
+        output.into(),
+        pages[0].texture_descriptor.format.is_srgb(),
+        pages[0].asset_usage,
+    ))
 }
+
 /// The loader responsible for loading `RpackAtlasAsset` files from `.rpack_gen.json` files.
-#[derive(Default)]
-pub struct RpackAtlasGenConfigLoader;
+///
+/// Source images matching the `asset_patterns` are packed in memory and tracked as dependencies,
+/// so the atlas is packed again when they change and hot reloading is enabled.
+/// In processed mode the [`RpackAtlasGenProcessor`](crate::prelude::RpackAtlasGenProcessor) uses it
+/// to pack the atlas ahead of time.
+/// Atlases are built with the same [`Spritesheet::build`] as in `rpack_cli`, including `.slice.json` and
+/// `.pivot.json` files next to the images, options of [`AtlasGenConfig`] used only for output files are ignored.
+#[derive(TypePath)]
+pub struct RpackAtlasGenConfigLoader {
+    asset_server: AssetServer,
+}
+
+impl AssetLoader for RpackAtlasGenConfigLoader {
+    type Asset = RpackAtlasAsset;
+    type Settings = RpackAtlasAssetLoaderSettings;
+    type Error = RpackAtlasAssetError;
+
+    fn extensions(&self) -> &[&str] {
+        &["rpack_gen.json"]
+    }
+
+    async fn load(
+        &self,
+        reader: &mut dyn bevy_asset::io::Reader,
+        settings: &RpackAtlasAssetLoaderSettings,
+        load_context: &mut bevy_asset::LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut file = String::new();
+        reader.read_to_string(&mut file).await?;
+        let config: AtlasGenConfig = serde_json::from_str(&file)?;
+
+        let source = load_context.path().source().clone_owned();
+        let dir = load_context
+            .path()
+            .path()
+            .parent()
+            .unwrap_or(Path::new(""))
+            .to_path_buf();
+        let paths = self.source_paths(&config, &source, &dir).await?;
+        let keys = frame_keys(
+            &paths
+                .iter()
+                .map(|p| p.to_string_lossy().into_owned())
+                .collect::<Vec<_>>(),
+        );
+
+        let mut build_config = SpritesheetBuildConfig::from(&config);
+        let mut images = Vec::with_capacity(paths.len());
+        for (path, key) in paths.iter().zip(keys) {
+            // Files that are not images are skipped like by `rpack_cli`, images that fail to decode are errors
+            if image::ImageFormat::from_path(path).is_err() {
+                continue;
+            }
+            let bytes = self.read_source_image(load_context, path, &source).await?;
+            let image = image::load_from_memory(&bytes).map_err(|e| {
+                RpackAtlasAssetError::SourceImages(format!("{}: {e}", path.display()))
+            })?;
+            let slice = match self
+                .read_sidecar(load_context, path, "slice.json", &source)
+                .await?
+            {
+                Some(slice) => Some(slice),
+                None => config.nine_slice_for(&key)?,
+            };
+            if let Some(slice) = slice {
+                build_config.nine_slices.insert(key.clone(), slice);
+            }
+            let pivot = match self
+                .read_sidecar(load_context, path, "pivot.json", &source)
+                .await?
+            {
+                Some(pivot) => Some(pivot),
+                None => config.pivot_for(&key)?,
+            };
+            if let Some(pivot) = pivot {
+                build_config.pivots.insert(key.clone(), pivot);
+            }
+            images.push(ImageFile { id: key, image });
+        }
+        // Pages are not written to files, the `RpackAtlasAssetSaver` names them when saving the atlas
+        let spritesheet =
+            Spritesheet::build(build_config, &images, "").map_err(GenerationError::from)?;
+
+        let mut pages = Vec::with_capacity(spritesheet.additional_pages.len() + 1);
+        let mut layouts = Vec::with_capacity(pages.capacity());
+        for page in spritesheet.pages() {
+            let mut image = Image::from_dynamic(page.clone(), true, RenderAssetUsages::default());
+            image.sampler = settings.image_sampler.clone();
+            layouts.push(TextureAtlasLayout::new_empty(image.size()));
+            pages.push(image);
+        }
+        // Rotated frames are restored on a new page when creating the atlas, so the saver keeps the packed description
+        let asset = spritesheet.atlas_asset;
+        load_context.add_labeled_asset(ATLAS_DESCRIPTION_LABEL.to_string(), asset.clone());
+        create_atlas_asset(asset, pages, layouts, settings, load_context)
+    }
+}
+
+/// Label of the [`AtlasAsset`] packed by the [`RpackAtlasGenConfigLoader`], before rotated frames are restored.
+pub(crate) const ATLAS_DESCRIPTION_LABEL: &str = "atlas_description";
+
+impl RpackAtlasGenConfigLoader {
+    /// Reads the file next to the source image at `path` with its extension replaced by `extension`,
+    /// `None` if it does not exist.
+    async fn read_sidecar<T: serde::de::DeserializeOwned>(
+        &self,
+        load_context: &mut bevy_asset::LoadContext<'_>,
+        path: &Path,
+        extension: &str,
+        source: &AssetSourceId<'static>,
+    ) -> Result<Option<T>, RpackAtlasAssetError> {
+        let sidecar = path.with_extension(extension);
+        let to_error = |e: &dyn std::fmt::Display| {
+            RpackAtlasAssetError::SourceImages(format!("{}: {e}", sidecar.display()))
+        };
+        // Sidecar files have no loader and are copied unchanged when processing,
+        // reading them through the load context makes existing ones dependencies of the atlas
+        let bytes = match load_context
+            .read_asset_bytes(AssetPath::from_path(&sidecar).with_source(source.clone()))
+            .await
+        {
+            Ok(bytes) => bytes,
+            Err(ReadAssetBytesError::AssetReaderError(AssetReaderError::NotFound(_))) => {
+                return Ok(None);
+            }
+            Err(e) => return Err(to_error(&e)),
+        };
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| to_error(&e))
+    }
+
+    /// Reads the unprocessed bytes of the source image at `path`.
+    async fn read_source_image(
+        &self,
+        load_context: &mut bevy_asset::LoadContext<'_>,
+        path: &Path,
+        source: &AssetSourceId<'static>,
+    ) -> Result<Vec<u8>, RpackAtlasAssetError> {
+        let to_error = |e: &dyn std::fmt::Display| {
+            RpackAtlasAssetError::SourceImages(format!("{}: {e}", path.display()))
+        };
+        // Reading through the load context makes the image a dependency of the atlas
+        let bytes = load_context
+            .read_asset_bytes(AssetPath::from_path(path).with_source(source.clone()))
+            .await
+            .map_err(|e| to_error(&e))?;
+        if self.asset_server.mode() == AssetServerMode::Unprocessed {
+            return Ok(bytes);
+        }
+        // Processed images may be compressed or converted, so the source file is packed instead
+        let reader = self
+            .asset_server
+            .get_source(source.clone())
+            .map_err(|e| to_error(&e))?
+            .reader();
+        let mut bytes = Vec::new();
+        reader
+            .read(path)
+            .await
+            .map_err(|e| to_error(&e))?
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(|e| to_error(&e))?;
+        Ok(bytes)
+    }
+
+    /// Sorted paths of files matching the patterns of `config`, which are relative to `dir`.
+    async fn source_paths(
+        &self,
+        config: &AtlasGenConfig,
+        source: &AssetSourceId<'static>,
+        dir: &Path,
+    ) -> Result<Vec<PathBuf>, RpackAtlasAssetError> {
+        let source = self
+            .asset_server
+            .get_source(source.clone())
+            .map_err(|e| RpackAtlasAssetError::SourceImages(e.to_string()))?;
+        // Source files are listed even in processed mode, so the `RpackAtlasGenProcessor`
+        // finds images that are not processed yet, reading them waits for their processing
+        let reader = source.reader();
+        let mut paths = BTreeSet::new();
+        for pattern in config.asset_patterns.iter() {
+            let pattern = dir.join(pattern);
+            let matcher = glob::Pattern::new(&pattern.to_string_lossy())
+                .map_err(GenerationError::InvalidPattern)?;
+            // Only the part of the tree that can match is listed
+            let root: PathBuf = pattern
+                .components()
+                .take_while(|c| !c.as_os_str().to_string_lossy().contains(['*', '?', '[']))
+                .collect();
+            let mut files = Vec::new();
+            list_files(reader, &root, &mut files)
+                .await
+                .map_err(|e| RpackAtlasAssetError::SourceImages(e.to_string()))?;
+            paths.extend(
+                files
+                    .into_iter()
+                    .filter(|p| matches_source_pattern(&matcher, p)),
+            );
+        }
+        Ok(paths.into_iter().collect())
+    }
+}
+
+/// Returns true when `path` matches `pattern` like with `glob::glob` used by `rpack_cli`,
+/// `*` does not match path separators, so only `**` includes files from subdirectories.
+fn matches_source_pattern(pattern: &glob::Pattern, path: &Path) -> bool {
+    pattern.matches_path_with(
+        path,
+        glob::MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        },
+    )
+}
+
+/// Adds the file at `path` or all files in the directory at `path` and its subdirectories to `files`.
+async fn list_files(
+    reader: &dyn ErasedAssetReader,
+    path: &Path,
+    files: &mut Vec<PathBuf>,
+) -> Result<(), AssetReaderError> {
+    match reader.is_directory(path).await {
+        Ok(true) => {
+            let mut children = reader.read_directory(path).await?;
+            while let Some(child) = children.next().await {
+                Box::pin(list_files(reader, &child, files)).await?;
+            }
+        }
+        Ok(false) => files.push(path.to_path_buf()),
+        Err(AssetReaderError::NotFound(_)) => {}
+        Err(e) => return Err(e),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_patterns_do_not_cross_directories() {
+        let pattern = glob::Pattern::new("sprites/*").unwrap();
+        assert!(matches_source_pattern(&pattern, Path::new("sprites/a.png")));
+        assert!(!matches_source_pattern(
+            &pattern,
+            Path::new("sprites/nested/b.png")
+        ));
+
+        let pattern = glob::Pattern::new("sprites/**/*.png").unwrap();
+        assert!(matches_source_pattern(&pattern, Path::new("sprites/a.png")));
+        assert!(matches_source_pattern(
+            &pattern,
+            Path::new("sprites/nested/b.png")
+        ));
+    }
+
+    #[test]
+    fn data_uri_pages_are_decoded() {
+        let (extension, bytes) = decode_data_uri("image/png;base64,iVBORw==").unwrap();
+        assert_eq!(extension, "png");
+        assert_eq!(bytes, [0x89, b'P', b'N', b'G']);
+        assert_eq!(
+            page_description("data:image/png;base64,iVBORw=="),
+            "data:image/png"
+        );
+
+        assert!(decode_data_uri("image/png,iVBORw==").is_err());
+        assert!(decode_data_uri("text/plain;base64,iVBORw==").is_err());
+    }
+}
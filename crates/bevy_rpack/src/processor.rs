@@ -0,0 +1,146 @@
+use crate::AtlasAsset;
+use crate::plugin::{
+    ATLAS_DESCRIPTION_LABEL, RpackAtlasAsset, RpackAtlasAssetError, RpackAtlasAssetLoader,
+    RpackAtlasAssetLoaderSettings, RpackAtlasGenConfigLoader, page_labels,
+};
+use base64::Engine;
+use bevy_asset::io::Writer;
+use bevy_asset::processor::LoadTransformAndSave;
+use bevy_asset::saver::{AssetSaver, SavedAsset};
+use bevy_asset::transformer::IdentityAssetTransformer;
+use bevy_asset::{AssetPath, AsyncWriteExt};
+use bevy_image::Image;
+use bevy_reflect::TypePath;
+use serde::{Deserialize, Serialize};
+
+/// Asset processor packing `.rpack_gen.json` files ahead of time, so processed builds ship packed atlases.
+///
+/// [`RpackAssetPlugin`](crate::prelude::RpackAssetPlugin) registers it as the default processor of `.rpack_gen.json` files.
+/// The source images are packed by the [`RpackAtlasGenConfigLoader`] with the same options as in `rpack_cli`,
+/// and the [`RpackAtlasAssetSaver`] saves the atlas in the `.rpack.json` format loaded by the [`RpackAtlasAssetLoader`],
+/// so loading the processed file does not read the source images.
+pub type RpackAtlasGenProcessor = LoadTransformAndSave<
+    RpackAtlasGenConfigLoader,
+    IdentityAssetTransformer<RpackAtlasAsset>,
+    RpackAtlasAssetSaver,
+>;
+
+/// Configuration settings for the [`RpackAtlasAssetSaver`].
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RpackAtlasAssetSaverSettings {
+    /// Compresses the page images into the Basis Universal format, which requires the `compressed_atlases` feature.
+    ///
+    /// The default is `false`, which saves the pages as PNG images.
+    pub compress: bool,
+}
+
+/// Saves a [`RpackAtlasAsset`] packed by the [`RpackAtlasGenConfigLoader`] as a `.rpack.json` file,
+/// loaded by the [`RpackAtlasAssetLoader`].
+///
+/// A processed asset is a single file, so the page images are embedded in the file names of the pages
+/// as `data:` URIs with base64 encoded PNG or Basis Universal images.
+#[derive(Default, TypePath)]
+pub struct RpackAtlasAssetSaver;
+
+impl AssetSaver for RpackAtlasAssetSaver {
+    type Asset = RpackAtlasAsset;
+    type Settings = RpackAtlasAssetSaverSettings;
+    type OutputLoader = RpackAtlasAssetLoader;
+    type Error = RpackAtlasAssetError;
+
+    async fn save(
+        &self,
+        writer: &mut Writer,
+        asset: SavedAsset<'_, '_, Self::Asset>,
+        settings: &Self::Settings,
+        asset_path: AssetPath<'_>,
+    ) -> Result<RpackAtlasAssetLoaderSettings, Self::Error> {
+        let Some(description) = asset.get_labeled::<AtlasAsset>(ATLAS_DESCRIPTION_LABEL) else {
+            return Err(RpackAtlasAssetError::ProcessedAtlas(
+                "missing atlas description".to_string(),
+            ));
+        };
+        let mut description = description.get().clone();
+        let page_count = description.pages().len();
+        let mut filenames = Vec::with_capacity(page_count);
+        let mut loader_settings = RpackAtlasAssetLoaderSettings::default();
+        for index in 0..page_count {
+            let (label, _) = page_labels(index);
+            let Some(image) = asset.get_labeled::<Image>(label.as_str()) else {
+                return Err(RpackAtlasAssetError::ProcessedAtlas(format!(
+                    "missing page image {label}"
+                )));
+            };
+            let image = image.get();
+            if index == 0 {
+                loader_settings.image_sampler = image.sampler.clone();
+            }
+            let (bytes, media_type) = if settings.compress {
+                (compress_page(image, &asset_path).await?, "image/basis")
+            } else {
+                (encode_page(image)?, "image/png")
+            };
+            let data = base64::engine::general_purpose::STANDARD.encode(bytes);
+            filenames.push(format!("data:{media_type};base64,{data}"));
+        }
+        for (page, filename) in description.pages.iter_mut().zip(filenames.iter()) {
+            page.filename = filename.clone();
+        }
+        if let Some(filename) = filenames.into_iter().next() {
+            description.filename = filename;
+        }
+
+        writer.write_all(&serde_json::to_vec(&description)?).await?;
+        Ok(loader_settings)
+    }
+}
+
+/// Encodes the page image as PNG.
+fn encode_page(image: &Image) -> Result<Vec<u8>, RpackAtlasAssetError> {
+    let dynamic = image
+        .clone()
+        .try_into_dynamic()
+        .map_err(|e| RpackAtlasAssetError::ProcessedAtlas(e.to_string()))?;
+    let mut bytes = Vec::new();
+    dynamic
+        .write_to(
+            &mut std::io::Cursor::new(&mut bytes),
+            image::ImageFormat::Png,
+        )
+        .map_err(|e| RpackAtlasAssetError::ProcessedAtlas(e.to_string()))?;
+    Ok(bytes)
+}
+
+/// Compresses the page image into the Basis Universal format.
+#[cfg(feature = "compressed_atlases")]
+async fn compress_page(
+    image: &Image,
+    path: &AssetPath<'_>,
+) -> Result<Vec<u8>, RpackAtlasAssetError> {
+    use bevy_asset::{ErasedLoadedAsset, LoadedAsset};
+    use bevy_image::CompressedImageSaver;
+
+    let loaded: ErasedLoadedAsset = LoadedAsset::from(image.clone()).into();
+    let Some(saved) = SavedAsset::from_loaded(&loaded) else {
+        return Err(RpackAtlasAssetError::ProcessedAtlas(
+            "page is not an image".to_string(),
+        ));
+    };
+    let mut bytes = Vec::new();
+    CompressedImageSaver
+        .save(&mut bytes, saved, &(), path.clone())
+        .await
+        .map_err(|e| RpackAtlasAssetError::ProcessedAtlas(e.to_string()))?;
+    Ok(bytes)
+}
+
+/// Compressing pages is not available without the `compressed_atlases` feature.
+#[cfg(not(feature = "compressed_atlases"))]
+async fn compress_page(
+    _image: &Image,
+    _path: &AssetPath<'_>,
+) -> Result<Vec<u8>, RpackAtlasAssetError> {
+    Err(RpackAtlasAssetError::ProcessedAtlas(
+        "compressing pages requires the `compressed_atlases` feature".to_string(),
+    ))
+}
@@ -0,0 +1,606 @@
+//! Building atlases from images, shared by `rpack_cli` and the asset processor.
+
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::Path;
+
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use serde_json::Value;
+use texture_packer::{Rect, TexturePackerConfig, importer::ImageImporter};
+use thiserror::Error;
+
+use crate::compositing::{self, ExtrusionFill};
+use crate::generation::{self, AnimationConfig, frame_key};
+use crate::packer::{self, AtlasPacker, AutoSizeConfig, PackingAlgorithm, SortHeuristic};
+use crate::{
+    AtlasAnimation, AtlasAsset, AtlasFrame, AtlasFrameSlice, AtlasFrameTrim, AtlasMetadata,
+    AtlasPage, SerializableRect,
+};
+
+/// Atlas built from images, with the page images and its `.rpack.json` description.
+#[derive(Clone)]
+pub struct Spritesheet {
+    /// Image of the first atlas page.
+    pub image_data: DynamicImage,
+    /// Images of the pages following the first one, empty for single page atlases.
+    pub additional_pages: Vec<DynamicImage>,
+    /// Description of the atlas.
+    pub atlas_asset: AtlasAsset,
+    /// `atlas_asset` serialized as written to the `.rpack.json` file.
+    pub atlas_asset_json: Value,
+    /// Heuristic used for ordering images before packing, never `SortHeuristic::Best`.
+    pub sort_heuristic: SortHeuristic,
+    /// Pixel-identical images packed only once.
+    pub deduplication: DeduplicationSummary,
+}
+
+/// Summary of images that were packed once and shared by multiple frames.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DeduplicationSummary {
+    /// Number of frames reusing the area of another frame.
+    pub aliased_frames: usize,
+    /// Number of pixels not packed thanks to the reuse.
+    pub saved_pixels: u64,
+}
+
+impl DeduplicationSummary {
+    /// Size of saved pixels in uncompressed RGBA8 format.
+    pub fn saved_bytes(&self) -> u64 {
+        self.saved_pixels * 4
+    }
+}
+
+impl Spritesheet {
+    /// Serializes `atlas_asset` again after it was modified.
+    pub fn rebuild_json(&mut self) {
+        if let Ok(value) = serde_json::to_value(&self.atlas_asset) {
+            self.atlas_asset_json = value;
+        }
+    }
+
+    /// Returns images of all atlas pages in order.
+    pub fn pages(&self) -> impl Iterator<Item = &DynamicImage> {
+        std::iter::once(&self.image_data).chain(self.additional_pages.iter())
+    }
+}
+
+/// Returns the filename of the atlas page with given index, `tilemap.png` becomes `tilemap_1.png`.
+pub fn page_filename(filename: &str, page: usize) -> String {
+    match filename.rsplit_once('.') {
+        Some((name, extension)) => format!("{name}_{page}.{extension}"),
+        None => format!("{filename}_{page}"),
+    }
+}
+
+/// Source image of an atlas frame.
+#[derive(Clone, PartialEq)]
+pub struct ImageFile {
+    /// Key of the frame.
+    pub id: String,
+    /// Pixels of the image.
+    pub image: DynamicImage,
+}
+
+impl ImageFile {
+    /// Reads the image file at `path`, its key is made from `id` with [`frame_key`]. `None` if it is not an image.
+    pub fn at_path<P>(path: &Path, id: P) -> Option<ImageFile>
+    where
+        P: AsRef<str>,
+    {
+        let id = frame_key(id.as_ref());
+        if let Ok(image) = ImageImporter::import_from_file(path) {
+            Some(ImageFile { image, id })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the image with borders made of pixels with alpha lower or equal to `alpha_threshold` removed.
+    ///
+    /// Fully transparent images are reduced to a single pixel.
+    pub fn trimmed(&self, alpha_threshold: u8) -> (DynamicImage, Option<AtlasFrameTrim>) {
+        let (width, height) = self.image.dimensions();
+        let mut min = [width, height];
+        let mut max = [0, 0];
+        for (x, y, pixel) in self.image.pixels() {
+            if pixel[3] > alpha_threshold {
+                min = [min[0].min(x), min[1].min(y)];
+                max = [max[0].max(x), max[1].max(y)];
+            }
+        }
+        if min[0] > max[0] || min[1] > max[1] {
+            min = [0, 0];
+            max = [0, 0];
+        }
+        let (w, h) = (max[0] - min[0] + 1, max[1] - min[1] + 1);
+        if w == width && h == height {
+            return (self.image.clone(), None);
+        }
+        (
+            self.image.crop_imm(min[0], min[1], w, h),
+            Some(AtlasFrameTrim {
+                offset: min,
+                source_size: [width, height],
+            }),
+        )
+    }
+}
+
+/// Splits `images` into unique images and aliases, pairs of the key and the index of the unique image it duplicates.
+fn deduplicate_images(
+    images: Vec<ImageFile>,
+) -> (Vec<ImageFile>, Vec<(String, usize)>, DeduplicationSummary) {
+    let mut unique: Vec<(ImageFile, RgbaImage)> = Vec::with_capacity(images.len());
+    let mut by_hash: HashMap<u64, Vec<usize>> = HashMap::new();
+    let mut aliases = Vec::new();
+    let mut summary = DeduplicationSummary::default();
+    for image in images {
+        let pixels = image.image.to_rgba8();
+        let mut hasher = DefaultHasher::new();
+        pixels.dimensions().hash(&mut hasher);
+        pixels.as_raw().hash(&mut hasher);
+        let candidates = by_hash.entry(hasher.finish()).or_default();
+        if let Some(&original) = candidates.iter().find(|&&i| unique[i].1 == pixels) {
+            summary.aliased_frames += 1;
+            summary.saved_pixels += pixels.width() as u64 * pixels.height() as u64;
+            aliases.push((image.id, original));
+        } else {
+            candidates.push(unique.len());
+            unique.push((image, pixels));
+        }
+    }
+    let unique = unique.into_iter().map(|(image, _)| image).collect();
+    (unique, aliases, summary)
+}
+
+/// Errors that can occur while building a `Spritesheet`.
+#[non_exhaustive]
+#[derive(Debug, Error, Clone)]
+pub enum SpritesheetError {
+    /// The image with given key does not fit into an empty page.
+    #[error("Cannot pack image: {0}")]
+    CannotPackImage(String),
+    /// There are no images to create the atlas from.
+    #[error("Failed to export tilemap image")]
+    FailedToExportImage,
+    /// The atlas description could not be serialized.
+    #[error("could not parse asset: {0}")]
+    ParsingError(String),
+    /// The images do not fit into the atlas.
+    #[error("Failed to pack image into tilemap, tilemap to small")]
+    FailedToPackImage,
+    /// An explicitly defined animation uses a key that is not in the atlas.
+    #[error("Animation {0} references missing frame {1}")]
+    MissingAnimationFrame(String, String),
+    /// The atlas cannot be described in an additional format.
+    #[error("Cannot export atlas: {0}")]
+    ExportError(String),
+}
+
+/// Configuration for building a `Spritesheet`.
+#[derive(Debug, Clone)]
+pub struct SpritesheetBuildConfig {
+    /// Configuration for the texture packer.
+    pub packer_config: TexturePackerConfig,
+    /// Whether to skip metadata serialization.
+    pub skip_metadata_serialization: bool,
+    /// Whether images that do not fit are placed on additional pages instead of failing.
+    pub allow_multiple_pages: bool,
+    /// Whether transparent borders are removed from images before packing.
+    pub trim: bool,
+    /// Pixels with alpha lower or equal to this value are treated as transparent when trimming.
+    pub trim_alpha_threshold: u8,
+    /// Defines how the extrusion area around frames is filled.
+    pub extrusion_fill: ExtrusionFill,
+    /// Whether rotated images are rotated 90 degrees counter-clockwise, like libGDX atlases expect, instead of clockwise.
+    pub counter_clockwise: bool,
+    /// Algorithm used for placing images on atlas pages.
+    pub packing_algorithm: PackingAlgorithm,
+    /// Shrinks the atlas to the smallest size fitting all images, `packer_config` size is the upper bound.
+    pub auto_size: Option<AutoSizeConfig>,
+    /// Order in which images are packed.
+    pub sort_heuristic: SortHeuristic,
+    /// Whether pixel-identical images are packed once and shared by their frames.
+    pub deduplicate: bool,
+    /// Nine-slice borders of images by their id, defined for the untrimmed image.
+    pub nine_slices: HashMap<String, AtlasFrameSlice>,
+    /// Pivot points of images by their id, relative to the untrimmed image size.
+    pub pivots: HashMap<String, [f32; 2]>,
+    /// Whether keys ending with a frame number are grouped into animations.
+    pub detect_animations: bool,
+    /// Duration of animation frames in milliseconds when not defined explicitly.
+    pub animation_frame_duration: u32,
+    /// Explicitly defined animations, replacing detected ones with the same name.
+    pub animations: Vec<AnimationConfig>,
+}
+
+impl From<TexturePackerConfig> for SpritesheetBuildConfig {
+    fn from(config: TexturePackerConfig) -> Self {
+        Self {
+            packer_config: config,
+            skip_metadata_serialization: false,
+            allow_multiple_pages: false,
+            trim: false,
+            trim_alpha_threshold: 0,
+            extrusion_fill: ExtrusionFill::default(),
+            counter_clockwise: false,
+            packing_algorithm: PackingAlgorithm::default(),
+            auto_size: None,
+            sort_heuristic: SortHeuristic::default(),
+            deduplicate: false,
+            nine_slices: HashMap::new(),
+            pivots: HashMap::new(),
+            detect_animations: false,
+            animation_frame_duration: AtlasAnimation::DEFAULT_FRAME_DURATION,
+            animations: Vec::new(),
+        }
+    }
+}
+
+impl Spritesheet {
+    /// Packs `images` into atlas pages, `filename` is the name of the first page image.
+    ///
+    /// Additional pages are named with [`page_filename`].
+    pub fn build<P>(
+        config: impl Into<SpritesheetBuildConfig>,
+        images: &[ImageFile],
+        filename: P,
+    ) -> Result<Self, SpritesheetError>
+    where
+        P: AsRef<str>,
+    {
+        let SpritesheetBuildConfig {
+            packer_config: config,
+            skip_metadata_serialization,
+            allow_multiple_pages,
+            trim,
+            trim_alpha_threshold,
+            extrusion_fill,
+            counter_clockwise,
+            packing_algorithm,
+            auto_size,
+            sort_heuristic,
+            deduplicate,
+            nine_slices,
+            pivots,
+            detect_animations,
+            animation_frame_duration,
+            animations,
+        } = config.into();
+        let mut trims = HashMap::new();
+        let images: Vec<ImageFile> = images
+            .iter()
+            .map(|image| {
+                if !trim {
+                    return image.clone();
+                }
+                let (trimmed, frame_trim) = image.trimmed(trim_alpha_threshold);
+                if let Some(frame_trim) = frame_trim {
+                    trims.insert(image.id.clone(), frame_trim);
+                }
+                ImageFile {
+                    id: image.id.clone(),
+                    image: trimmed,
+                }
+            })
+            .collect();
+        let (images, aliases, deduplication) = if deduplicate {
+            deduplicate_images(images)
+        } else {
+            (images, Vec::new(), DeduplicationSummary::default())
+        };
+        let sizes: Vec<(u32, u32)> = images.iter().map(|i| i.image.dimensions()).collect();
+        let heuristics = match sort_heuristic {
+            SortHeuristic::Best => SortHeuristic::CANDIDATES.as_slice(),
+            _ => std::slice::from_ref(&sort_heuristic),
+        };
+        let mut best: Option<(SortHeuristic, Layout)> = None;
+        let mut error = None;
+        for &heuristic in heuristics {
+            match Layout::pack(
+                &sizes,
+                &heuristic.order(&sizes),
+                config,
+                packing_algorithm,
+                auto_size,
+                allow_multiple_pages,
+            ) {
+                Ok(layout) => {
+                    if best
+                        .as_ref()
+                        .is_none_or(|(_, best)| layout.score() < best.score())
+                    {
+                        best = Some((heuristic, layout));
+                    }
+                }
+                Err(index) => {
+                    error.get_or_insert(index);
+                }
+            }
+        }
+        let Some((sort_heuristic, layout)) = best else {
+            let id = error.map_or_else(String::new, |index| images[index].id.clone());
+            return Err(SpritesheetError::CannotPackImage(id));
+        };
+        let config = layout.config;
+        let extrusion = config.texture_extrusion;
+        let mut frames: Vec<AtlasFrame> = layout
+            .placements
+            .iter()
+            .map(|&(i, page, rect, rotated)| AtlasFrame {
+                key: images[i].id.clone(),
+                frame: SerializableRect {
+                    x: rect.x,
+                    y: rect.y,
+                    w: rect.w,
+                    h: rect.h,
+                },
+                page,
+                trim: trims.get(&images[i].id).copied(),
+                rotated,
+                counter_clockwise: rotated && counter_clockwise,
+                slice: nine_slices.get(&images[i].id).map(|slice| {
+                    let (w, h) = images[i].image.dimensions();
+                    slice.trimmed(trims.get(&images[i].id), [w, h])
+                }),
+                pivot: pivots.get(&images[i].id).copied(),
+            })
+            .collect();
+        let mut pages =
+            vec![RgbaImage::new(config.max_width, config.max_height); layout.page_count];
+        for &(i, page, rect, rotated) in layout.placements.iter() {
+            compositing::draw_frame(
+                &mut pages[page],
+                &images[i].image,
+                (rect.x, rect.y),
+                rotated.then_some(counter_clockwise),
+                extrusion,
+                extrusion_fill,
+            );
+        }
+        // Aliases point at the frame of the image they duplicate
+        let frame_by_image: HashMap<usize, usize> = layout
+            .placements
+            .iter()
+            .enumerate()
+            .map(|(frame, &(i, ..))| (i, frame))
+            .collect();
+        for (key, i) in aliases {
+            let mut frame = frames[frame_by_image[&i]].clone();
+            frame.trim = trims.get(&key).copied();
+            frame.slice = nine_slices
+                .get(&key)
+                .map(|slice| slice.trimmed(frame.trim.as_ref(), frame.size()));
+            frame.pivot = pivots.get(&key).copied();
+            frame.key = key;
+            frames.push(frame);
+        }
+        let pages: Vec<DynamicImage> = pages.into_iter().map(DynamicImage::from).collect();
+        let filename = filename.as_ref();
+        let atlas_pages = if pages.len() > 1 {
+            pages
+                .iter()
+                .enumerate()
+                .map(|(i, image)| AtlasPage {
+                    size: [image.width(), image.height()],
+                    filename: page_filename(filename, i),
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let mut atlas_animations = if detect_animations {
+            generation::detect_animations(
+                frames.iter().map(|f| f.key.as_str()),
+                animation_frame_duration,
+            )
+        } else {
+            Default::default()
+        };
+        for animation in animations.iter() {
+            if let Some(key) = animation
+                .frames
+                .iter()
+                .find(|key| !frames.iter().any(|f| &f.key == *key))
+            {
+                return Err(SpritesheetError::MissingAnimationFrame(
+                    animation.name.clone(),
+                    key.clone(),
+                ));
+            }
+            atlas_animations.insert(
+                animation.name.clone(),
+                animation.to_animation(animation_frame_duration),
+            );
+        }
+
+        let mut atlas_asset = AtlasAsset {
+            metadata: AtlasMetadata {
+                skip_serialization: skip_metadata_serialization,
+                ..Default::default()
+            },
+            size: [pages[0].width(), pages[0].height()],
+            filename: atlas_pages
+                .first()
+                .map_or_else(|| filename.to_owned(), |p| p.filename.clone()),
+            frames,
+            pages: atlas_pages,
+            animations: atlas_animations,
+        };
+        atlas_asset.frames.sort_by(|a, b| a.key.cmp(&b.key));
+        let atlas_asset_json = serde_json::to_value(&atlas_asset)
+            .map_err(|e| SpritesheetError::ParsingError(e.to_string()))?;
+
+        let mut pages = pages.into_iter();
+        let image_data = pages.next().ok_or(SpritesheetError::FailedToExportImage)?;
+        Ok(Spritesheet {
+            image_data,
+            additional_pages: pages.collect(),
+            atlas_asset,
+            atlas_asset_json,
+            sort_heuristic,
+            deduplication,
+        })
+    }
+}
+
+/// Positions of packed images before the atlas pages are drawn.
+struct Layout {
+    /// Packer config with the final page size.
+    config: TexturePackerConfig,
+    page_count: usize,
+    /// Index of the image, its page, area in the atlas and whether it was rotated.
+    placements: Vec<(usize, usize, Rect, bool)>,
+}
+
+impl Layout {
+    /// Packs images in the given `order`, returns the index of the first image that does not fit on error.
+    fn pack(
+        sizes: &[(u32, u32)],
+        order: &[usize],
+        mut config: TexturePackerConfig,
+        algorithm: PackingAlgorithm,
+        auto_size: Option<AutoSizeConfig>,
+        allow_multiple_pages: bool,
+    ) -> Result<Self, usize> {
+        if let Some(auto_size) = auto_size {
+            let sizes: Vec<(u32, u32)> = order.iter().map(|&i| sizes[i]).collect();
+            // When nothing fits a single page the maximal size is used
+            if let Some((width, height)) =
+                packer::minimal_size(algorithm, config, &sizes, auto_size)
+            {
+                config.max_width = width;
+                config.max_height = height;
+            }
+        }
+        let mut packers = vec![AtlasPacker::new(algorithm, config)];
+        let mut placements = Vec::with_capacity(order.len());
+        for &i in order {
+            let (w, h) = sizes[i];
+            let page = match packers.iter().position(|p| p.can_pack(w, h)) {
+                Some(page) => page,
+                None if allow_multiple_pages => {
+                    let packer = AtlasPacker::new(algorithm, config);
+                    if !packer.can_pack(w, h) {
+                        return Err(i);
+                    }
+                    packers.push(packer);
+                    packers.len() - 1
+                }
+                None => return Err(i),
+            };
+            let (rect, rotated) = packers[page].pack(w, h).ok_or(i)?;
+            placements.push((i, page, rect, rotated));
+        }
+        Ok(Self {
+            config,
+            page_count: packers.len(),
+            placements,
+        })
+    }
+
+    /// Lower is better: fewer pages, then smaller pages, then smaller area covered by frames.
+    fn score(&self) -> (usize, u64, u64) {
+        let mut used = vec![(0u64, 0u64); self.page_count];
+        for (_, page, rect, _) in &self.placements {
+            let (w, h) = &mut used[*page];
+            *w = (*w).max((rect.x + rect.w) as u64);
+            *h = (*h).max((rect.y + rect.h) as u64);
+        }
+        (
+            self.page_count,
+            self.config.max_width as u64 * self.config.max_height as u64,
+            used.iter().map(|(w, h)| w * h).sum(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::AtlasGenConfig;
+
+    fn images(sizes: &[(u32, u32)]) -> Vec<ImageFile> {
+        sizes
+            .iter()
+            .enumerate()
+            .map(|(i, &(w, h))| ImageFile {
+                id: format!("image_{i}"),
+                image: RgbaImage::new(w, h).into(),
+            })
+            .collect()
+    }
+
+    fn build(
+        config: &AtlasGenConfig,
+        sizes: &[(u32, u32)],
+    ) -> Result<Spritesheet, SpritesheetError> {
+        Spritesheet::build(config, &images(sizes), "atlas.png")
+    }
+
+    #[test]
+    fn build_accepts_frames_ending_at_page_edge() {
+        let config = AtlasGenConfig {
+            size: Some(64),
+            ..Default::default()
+        };
+        for (w, h) in [(62, 62), (64, 64), (64, 30)] {
+            let spritesheet = build(&config, &[(w, h)]).unwrap();
+            assert!(spritesheet.additional_pages.is_empty());
+            let frame = spritesheet.atlas_asset.frames[0].frame;
+            assert_eq!(frame, SerializableRect { x: 0, y: 0, w, h });
+        }
+
+        let config = AtlasGenConfig {
+            size: Some(68),
+            border_padding: Some(2),
+            ..Default::default()
+        };
+        let spritesheet = build(&config, &[(64, 31), (64, 31)]).unwrap();
+        let frame = spritesheet.atlas_asset.frames[1].frame;
+        assert_eq!(frame.y + frame.h, 66);
+    }
+
+    #[test]
+    fn build_fails_once_the_page_is_full() {
+        let config = AtlasGenConfig {
+            size: Some(64),
+            ..Default::default()
+        };
+        assert!(matches!(
+            build(&config, &[(64, 64), (1, 1)]),
+            Err(SpritesheetError::CannotPackImage(key)) if key == "image_1"
+        ));
+        assert!(matches!(
+            build(&config, &[(65, 64)]),
+            Err(SpritesheetError::CannotPackImage(_))
+        ));
+
+        let config = AtlasGenConfig {
+            allow_multiple_pages: Some(true),
+            ..config
+        };
+        let spritesheet = build(&config, &[(64, 64), (1, 1)]).unwrap();
+        assert_eq!(spritesheet.additional_pages.len(), 1);
+        assert_eq!(spritesheet.atlas_asset.frames[1].page, 1);
+    }
+
+    #[test]
+    fn build_fills_earlier_pages_first() {
+        let config = AtlasGenConfig {
+            size: Some(64),
+            allow_multiple_pages: Some(true),
+            ..Default::default()
+        };
+        let spritesheet = build(&config, &[(64, 32), (64, 64), (16, 16)]).unwrap();
+        assert_eq!(spritesheet.additional_pages.len(), 1);
+        let pages: Vec<usize> = spritesheet
+            .atlas_asset
+            .frames
+            .iter()
+            .map(|f| f.page)
+            .collect();
+        assert_eq!(pages, [0, 1, 0]);
+    }
+}
@@ -0,0 +1,6 @@
+use bevy_rpack::AtlasAnimation;
+
+pub use bevy_rpack::generation::{AnimationConfig, detect_animations, sequence_frame};
+
+/// Duration of animation frames in milliseconds when none is configured.
+pub const DEFAULT_FRAME_DURATION: u32 = AtlasAnimation::DEFAULT_FRAME_DURATION;
@@ -0,0 +1,108 @@
+//! Helpers for generating atlases from `build.rs` scripts.
+//!
+//! ```rust,ignore
+//! // build.rs
+//! fn main() {
+//!     let out_dir = std::env::var("OUT_DIR").unwrap();
+//!     rpack_cli::build::generate("assets/tilemap.rpack_gen.json", out_dir).unwrap();
+//! }
+//! ```
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use crate::{SIDECAR_EXTENSIONS, TilemapGenerationConfig};
+
+/// Generates the atlas described by the `.rpack_gen.json` file at `config_path` into `out_dir`
+/// and prints `cargo:rerun-if-changed` lines for the config and all source images.
+///
+/// The atlas files and the `rust_module` keep their file names but are placed directly in `out_dir`,
+/// a relative `out_dir` is resolved against the current directory like `config_path`.
+pub fn generate(config_path: impl AsRef<Path>, out_dir: impl AsRef<Path>) -> anyhow::Result<()> {
+    let config_path = config_path.as_ref();
+    // Output paths are relative to the config, so `out_dir` must not depend on the current directory
+    let out_dir = std::path::absolute(out_dir.as_ref())?;
+    let out_dir = out_dir.as_path();
+    let mut config = TilemapGenerationConfig::read_from_file(config_path)?;
+    println!("cargo:rerun-if-changed={}", config_path.display());
+    for path in source_paths(&config) {
+        println!("cargo:rerun-if-changed={}", path.display());
+    }
+
+    std::fs::create_dir_all(out_dir)?;
+    config.output_path = in_dir(out_dir, &config.output_path);
+    config.rust_module = config.rust_module.map(|path| in_dir(out_dir, &path));
+    config.generate()
+}
+
+/// Returns `path` with its parent directories replaced by `dir`.
+fn in_dir(dir: &Path, path: &str) -> String {
+    let name = Path::new(path).file_name().unwrap_or_default();
+    dir.join(name).to_string_lossy().to_string()
+}
+
+/// Matched images, their existing sidecar files and directories containing them,
+/// so added images trigger the generation as well.
+fn source_paths(config: &TilemapGenerationConfig) -> BTreeSet<PathBuf> {
+    let (file_paths, _) = config.get_file_paths_and_prefix();
+    let mut paths = BTreeSet::new();
+    for path in file_paths {
+        for extension in SIDECAR_EXTENSIONS {
+            let sidecar = path.with_extension(extension);
+            if sidecar.is_file() {
+                paths.insert(sidecar);
+            }
+        }
+        if let Some(parent) = path.parent() {
+            paths.insert(parent.to_path_buf());
+        }
+        paths.insert(path);
+    }
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate_resolves_relative_out_dir_against_current_dir() {
+        let normal = |path: &Path| {
+            path.components()
+                .filter(|c| matches!(c, std::path::Component::Normal(_)))
+                .map(|c| PathBuf::from(c.as_os_str()))
+                .collect::<Vec<_>>()
+        };
+        let current_dir = normal(&std::env::current_dir().unwrap());
+        // Config is nested as deep as the current directory, so resolving `out_dir` against it stays inside
+        let config_root = tempfile::tempdir().unwrap();
+        let config_dir: PathBuf = std::iter::once(config_root.path().to_path_buf())
+            .chain(current_dir.iter().cloned())
+            .collect();
+        std::fs::create_dir_all(config_dir.join("tiles")).unwrap();
+        image::RgbaImage::new(4, 4)
+            .save(config_dir.join("tiles/tile.png"))
+            .unwrap();
+        let config_path = config_dir.join("tilemap.rpack_gen.json");
+        std::fs::write(
+            &config_path,
+            r#"{ "asset_patterns": ["tiles/*"], "output_path": "out/tilemap", "size": 16 }"#,
+        )
+        .unwrap();
+
+        // Relative path from the current directory to a unique temporary one
+        let out_root = tempfile::tempdir().unwrap();
+        let out_dir: PathBuf = current_dir
+            .iter()
+            .map(|_| PathBuf::from(".."))
+            .chain(normal(out_root.path()))
+            .collect::<PathBuf>()
+            .join("generated");
+        generate(&config_path, &out_dir).unwrap();
+
+        let generated = out_root.path().join("generated");
+        assert!(generated.join("tilemap.rpack.json").is_file());
+        assert!(generated.join("tilemap.png").is_file());
+        assert!(!config_dir.join(&out_dir).exists());
+    }
+}
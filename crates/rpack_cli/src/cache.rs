@@ -0,0 +1,93 @@
+//! Fingerprints of the generation inputs, used for skipping generation of unchanged atlases.
+
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{SIDECAR_EXTENSIONS, TilemapGenerationConfig};
+
+/// Extension of the cache file written next to the generated atlas.
+pub const CACHE_EXTENSION: &str = "rpack_cache";
+
+/// Everything that affects the generated atlas.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct InputFingerprint {
+    /// Version of rpack that generated the atlas.
+    pub rpack_version: String,
+    /// Serialized generation config.
+    pub config: String,
+    /// Source images and their sidecar files.
+    pub sources: Vec<SourceFingerprint>,
+}
+
+/// Path, size and modification time of a source file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SourceFingerprint {
+    pub path: PathBuf,
+    pub size: u64,
+    /// Modification time in nanoseconds since the Unix epoch.
+    pub modified: u64,
+}
+
+impl InputFingerprint {
+    /// Creates the fingerprint of `config` generated from images at `file_paths`.
+    pub fn new(config: &TilemapGenerationConfig, file_paths: &[PathBuf]) -> anyhow::Result<Self> {
+        let mut sources = Vec::with_capacity(file_paths.len());
+        for path in file_paths {
+            sources.push(SourceFingerprint::new(path)?);
+            for extension in SIDECAR_EXTENSIONS {
+                let sidecar = path.with_extension(extension);
+                if sidecar.is_file() {
+                    sources.push(SourceFingerprint::new(&sidecar)?);
+                }
+            }
+        }
+        Ok(Self {
+            rpack_version: env!("CARGO_PKG_VERSION").to_owned(),
+            config: serde_json::to_string(config)?,
+            sources,
+        })
+    }
+}
+
+impl SourceFingerprint {
+    fn new(path: &Path) -> anyhow::Result<Self> {
+        let metadata = std::fs::metadata(path)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+        Ok(Self {
+            path: path.to_path_buf(),
+            size: metadata.len(),
+            modified,
+        })
+    }
+}
+
+/// Contents of the cache file, inputs of the last generation and the files it wrote.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationCache {
+    pub inputs: InputFingerprint,
+    pub outputs: Vec<PathBuf>,
+}
+
+impl GenerationCache {
+    /// Reads the cache file, `None` if it is missing or invalid.
+    pub fn read(path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Returns `true` when the inputs did not change and all outputs still exist.
+    pub fn is_up_to_date(&self, inputs: &InputFingerprint) -> bool {
+        self.inputs == *inputs && self.outputs.iter().all(|path| path.is_file())
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+
+use bevy_rpack::AtlasAsset;
+
+/// Keywords that cannot be used as module names without the raw identifier prefix.
+const KEYWORDS: [&str; 48] = [
+    "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum", "extern", "false",
+    "fn", "for", "gen", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "static", "struct", "trait", "true", "try", "type", "unsafe", "use", "where",
+    "while", "abstract", "become", "box", "do", "final", "macro", "override", "priv", "typeof",
+    "unsized", "virtual", "yield",
+];
+
+/// Keywords that cannot be used even as raw identifiers.
+const RESERVED: [&str; 4] = ["self", "Self", "super", "crate"];
+
+/// Keys grouped by folders, constants of a module and its submodules.
+#[derive(Default)]
+struct Module<'a> {
+    constants: Vec<(&'a str, &'a str)>,
+    modules: BTreeMap<&'a str, Module<'a>>,
+}
+
+impl<'a> Module<'a> {
+    fn insert(&mut self, key: &'a str) {
+        let mut module = self;
+        let mut parts = key.split('/').peekable();
+        while let Some(part) = parts.next() {
+            if parts.peek().is_none() {
+                module.constants.push((part, key));
+            } else {
+                module = module.modules.entry(part).or_default();
+            }
+        }
+    }
+
+    fn write(&self, out: &mut String, depth: usize) {
+        let indent = "    ".repeat(depth);
+        let mut names = HashSet::new();
+        for (name, key) in self.constants.iter() {
+            let name = unique(&mut names, constant_name(name));
+            let _ = writeln!(out, "{indent}pub const {name}: &str = {key:?};");
+        }
+        let mut names = HashSet::new();
+        for (name, module) in self.modules.iter() {
+            let name = unique(&mut names, module_name(name));
+            let _ = writeln!(out, "{indent}pub mod {name} {{");
+            module.write(out, depth + 1);
+            let _ = writeln!(out, "{indent}}}");
+        }
+    }
+}
+
+/// Generates a Rust module with a constant for every frame key in `frames` and every animation in `animations`.
+///
+/// Folders of the keys become nested modules, `agents/spaceAstronauts_005` is available as
+/// `frames::agents::SPACE_ASTRONAUTS_005`.
+pub fn rust_module(atlas: &AtlasAsset) -> String {
+    let mut out = String::from("// Generated by rpack, do not edit.\n\n");
+    for (name, keys) in [
+        (
+            "frames",
+            atlas.frames.iter().map(|f| f.key.as_str()).collect(),
+        ),
+        (
+            "animations",
+            atlas
+                .animations
+                .keys()
+                .map(String::as_str)
+                .collect::<Vec<_>>(),
+        ),
+    ] {
+        let mut module = Module::default();
+        for key in keys {
+            module.insert(key);
+        }
+        let _ = writeln!(out, "#[allow(dead_code)]\npub mod {name} {{");
+        module.write(&mut out, 1);
+        let _ = writeln!(out, "}}");
+    }
+    out
+}
+
+/// Splits `name` into lowercase words, breaking on non-alphanumeric characters and camelCase humps.
+fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut previous: Option<char> = None;
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+        } else {
+            let hump = c.is_ascii_uppercase()
+                && previous.is_some_and(|p| p.is_ascii_lowercase() || p.is_ascii_digit());
+            if hump && !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            word.push(c.to_ascii_lowercase());
+        }
+        previous = Some(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn constant_name(name: &str) -> String {
+    identifier(words(name).join("_").to_uppercase())
+}
+
+fn module_name(name: &str) -> String {
+    let name = identifier(words(name).join("_"));
+    if RESERVED.contains(&name.as_str()) {
+        format!("{name}_")
+    } else if KEYWORDS.contains(&name.as_str()) {
+        format!("r#{name}")
+    } else {
+        name
+    }
+}
+
+/// Makes sure the identifier is not empty and does not start with a digit.
+fn identifier(name: String) -> String {
+    if name.is_empty() {
+        "unnamed".to_owned()
+    } else if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{name}")
+    } else {
+        name
+    }
+}
+
+/// Adds a numeric suffix to names already used in the module.
+fn unique(names: &mut HashSet<String>, name: String) -> String {
+    let mut candidate = name.clone();
+    let mut i = 2;
+    while !names.insert(candidate.clone()) {
+        candidate = format!("{name}_{i}");
+        i += 1;
+    }
+    candidate
+}
@@ -1,11 +1,69 @@
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
 
-use clap::Subcommand;
-use rpack_cli::TilemapGenerationConfig;
+use bevy_rpack::AtlasAsset;
+use clap::{Args, Subcommand};
 use rpack_cli::saving::SaveableImage;
+use rpack_cli::{TilemapGenerationConfig, compositing};
 
-use rpack_cli::SaveImageFormat;
+use rpack_cli::{ExportFormat, ExtrusionFill, PackingAlgorithm, SaveImageFormat, SortHeuristic};
+
+mod watch;
+
+/// Packing options shared by the commands creating tilemaps
+#[derive(Args, Debug, Clone, Default)]
+pub struct PackingArgs {
+    /// Size of the area around each frame filled according to `extrusion-fill` in pixel. Default value is `0`.
+    #[clap(long)]
+    texture_extrusion: Option<u32>,
+    /// Fill of the extrusion area: `transparent`, `edge-repeat` or `#rrggbb[aa]` color. Default value is `edge-repeat`.
+    #[clap(long)]
+    extrusion_fill: Option<ExtrusionFill>,
+    /// Algorithm used for placing images in the atlas. Default value is `skyline`.
+    #[clap(long)]
+    packing_algorithm: Option<PackingAlgorithm>,
+    /// Order in which images are packed, `best` tries all of them. Default value is `none`.
+    #[clap(long)]
+    sort_heuristic: Option<SortHeuristic>,
+    /// Width of the tilemap overriding `size`, the upper bound of the width with `auto-size`.
+    #[clap(long)]
+    max_width: Option<u32>,
+    /// Height of the tilemap overriding `size`, the upper bound of the height with `auto-size`.
+    #[clap(long)]
+    max_height: Option<u32>,
+    /// Shrinks the tilemap to the smallest size fitting all images, `size` becomes the upper bound.
+    #[clap(long)]
+    auto_size: bool,
+    /// Allows automatic sizes that are not powers of two.
+    #[clap(long)]
+    no_power_of_two: bool,
+    /// Allows automatic sizes with different width and height.
+    #[clap(long)]
+    allow_non_square: bool,
+    /// Additional atlas description format, argument can be passed multiple times
+    #[clap(long = "export-format")]
+    export_formats: Vec<ExportFormat>,
+    /// Path of a Rust file to generate with constants for all frame keys and animations
+    #[clap(long)]
+    rust_module: Option<String>,
+}
+
+impl PackingArgs {
+    fn apply(self, config: &mut TilemapGenerationConfig) {
+        config.texture_extrusion = self.texture_extrusion;
+        config.extrusion_fill = self.extrusion_fill;
+        config.packing_algorithm = self.packing_algorithm;
+        config.sort_heuristic = self.sort_heuristic;
+        config.max_width = self.max_width;
+        config.max_height = self.max_height;
+        config.auto_size = self.auto_size.then_some(true);
+        config.power_of_two = self.no_power_of_two.then_some(false);
+        config.allow_non_square = self.allow_non_square.then_some(true);
+        config.export_formats = self.export_formats;
+        config.rust_module = self.rust_module;
+    }
+}
 
 #[derive(Subcommand, Debug, Clone)]
 pub enum Commands {
@@ -27,6 +85,11 @@ pub enum Commands {
         texture_padding: Option<u32>,
         /// Size of the padding on the outer edge of the packed image in pixel. Default value is `0`.
         border_padding: Option<u32>,
+        #[command(flatten)]
+        packing: PackingArgs,
+        /// Generates the tilemap even if the inputs did not change since the last generation
+        #[clap(long)]
+        force: bool,
     },
     /// Creates a tilemap generation config
     ConfigCreate {
@@ -51,12 +114,26 @@ pub enum Commands {
         /// Size of the padding on the outer edge of the packed image in pixel. Default value is `0`.
         #[clap(short, long)]
         border_padding: Option<u32>,
+        #[command(flatten)]
+        packing: PackingArgs,
     },
     /// Generates a tilemap from config
     GenerateFromConfig {
         /// path of the config to use
         #[clap(action)]
         config_path: String,
+        /// Generates the tilemap even if the inputs did not change since the last generation
+        #[clap(long)]
+        force: bool,
+    },
+    /// Generates tilemaps from configs and regenerates them whenever their sources change
+    Watch {
+        /// paths of the configs to watch
+        #[clap(action, required = true)]
+        config_paths: Vec<PathBuf>,
+        /// Time in milliseconds to wait for further changes before regenerating
+        #[clap(long, default_value_t = 200)]
+        debounce: u64,
     },
     /// Converts a texture between formats
     Convert {
@@ -70,6 +147,24 @@ pub enum Commands {
         #[clap(long)]
         size: Option<u32>,
     },
+    /// Converts a TexturePacker JSON or libGDX `.atlas` file into `.rpack.json`
+    Import {
+        /// path of the atlas to import, `.atlas` files are read as libGDX atlases, `.json` files as TexturePacker JSON
+        #[clap(action)]
+        source_path: PathBuf,
+        /// path of the `.rpack.json` file to create, when no value is provided it is placed next to the source
+        #[clap(action)]
+        output_path: Option<PathBuf>,
+    },
+    /// Extracts frames of an atlas into separate images
+    Unpack {
+        /// path of the `.rpack.json` atlas, libGDX `.atlas` and TexturePacker `.json` files are accepted too
+        #[clap(action)]
+        atlas_path: PathBuf,
+        /// directory for the extracted images, when no value is provided uses '<atlas name>_unpacked' next to the atlas
+        #[clap(action)]
+        output_dir: Option<PathBuf>,
+    },
 }
 impl Commands {
     pub(crate) fn run(&self) -> anyhow::Result<()> {
@@ -81,6 +176,8 @@ impl Commands {
                 source_paths,
                 texture_padding,
                 border_padding,
+                packing,
+                force,
             } => Self::generate_tilemap(
                 name,
                 size,
@@ -88,6 +185,8 @@ impl Commands {
                 source_paths,
                 texture_padding,
                 border_padding,
+                packing,
+                force,
             ),
             Commands::ConfigCreate {
                 config_path,
@@ -97,6 +196,7 @@ impl Commands {
                 source_paths,
                 texture_padding,
                 border_padding,
+                packing,
             } => Self::create_config(
                 config_path,
                 output_path,
@@ -105,15 +205,28 @@ impl Commands {
                 source_paths,
                 texture_padding,
                 border_padding,
+                packing,
             ),
-            Commands::GenerateFromConfig { config_path } => {
-                Self::generate_tilemap_from_config(config_path)
+            Commands::GenerateFromConfig { config_path, force } => {
+                Self::generate_tilemap_from_config(config_path, force)
             }
+            Commands::Watch {
+                config_paths,
+                debounce,
+            } => watch::watch(&config_paths, Duration::from_millis(debounce)),
             Commands::Convert {
                 source_path,
                 output_path,
                 size,
             } => Self::convert(source_path, output_path, size),
+            Commands::Import {
+                source_path,
+                output_path,
+            } => Self::import(source_path, output_path),
+            Commands::Unpack {
+                atlas_path,
+                output_dir,
+            } => Self::unpack(atlas_path, output_dir),
         }
     }
 
@@ -130,6 +243,68 @@ impl Commands {
         Ok(())
     }
 
+    fn import(source_path: PathBuf, output_path: Option<PathBuf>) -> anyhow::Result<()> {
+        let content = std::fs::read_to_string(&source_path)?;
+        let filename = source_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let atlas = bevy_rpack::import::from_file_content(&filename, &content)?;
+        let output_path = output_path.unwrap_or_else(|| {
+            let name = filename.split('.').next().unwrap_or_default();
+            source_path.with_file_name(format!("{name}.rpack.json"))
+        });
+        let json = serde_json::to_string_pretty(&atlas)?;
+        std::fs::write(&output_path, json)?;
+        println!(
+            "Atlas with {} frames saved at: {}",
+            atlas.frames.len(),
+            output_path.display()
+        );
+        Ok(())
+    }
+
+    fn unpack(atlas_path: PathBuf, output_dir: Option<PathBuf>) -> anyhow::Result<()> {
+        let content = std::fs::read_to_string(&atlas_path)?;
+        let filename = atlas_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let atlas: AtlasAsset = if filename.ends_with(".rpack.json") {
+            serde_json::from_str(&content)?
+        } else {
+            bevy_rpack::import::from_file_content(&filename, &content)?
+        };
+        let output_dir = output_dir.unwrap_or_else(|| {
+            let name = filename.split('.').next().unwrap_or_default();
+            atlas_path.with_file_name(format!("{name}_unpacked"))
+        });
+        let pages = atlas
+            .pages()
+            .iter()
+            .map(|page| image::open(atlas_path.with_file_name(&page.filename)))
+            .collect::<Result<Vec<_>, _>>()?;
+        for frame in atlas.frames.iter() {
+            let Some(page) = pages.get(frame.page) else {
+                anyhow::bail!("frame {} references missing page {}", frame.key, frame.page);
+            };
+            let path = frame_output_path(&output_dir, &frame.key)?;
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            compositing::extract_frame(page, frame).save(&path)?;
+        }
+        println!(
+            "Extracted {} frames to: {}",
+            atlas.frames.len(),
+            output_dir.display()
+        );
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn generate_tilemap(
         name: Option<String>,
         size: Option<u32>,
@@ -137,6 +312,8 @@ impl Commands {
         source_paths: Vec<String>,
         texture_padding: Option<u32>,
         border_padding: Option<u32>,
+        packing: PackingArgs,
+        force: bool,
     ) -> anyhow::Result<()> {
         let name = name.unwrap_or("tilemap".to_owned());
         let source_paths = if source_paths.is_empty() {
@@ -144,7 +321,7 @@ impl Commands {
         } else {
             source_paths
         };
-        let config = TilemapGenerationConfig {
+        let mut config = TilemapGenerationConfig {
             asset_patterns: source_paths,
             output_path: name,
             format,
@@ -153,10 +330,16 @@ impl Commands {
             border_padding,
             ..Default::default()
         };
+        packing.apply(&mut config);
 
-        config.generate()
+        if force {
+            config.force_generate()
+        } else {
+            config.generate()
+        }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn create_config(
         config_path: String,
         output_path: Option<String>,
@@ -165,10 +348,11 @@ impl Commands {
         source_paths: Vec<String>,
         texture_padding: Option<u32>,
         border_padding: Option<u32>,
+        packing: PackingArgs,
     ) -> Result<(), anyhow::Error> {
         let name = output_path.unwrap_or("tilemap".to_owned());
 
-        let config = TilemapGenerationConfig {
+        let mut config = TilemapGenerationConfig {
             size,
             asset_patterns: source_paths,
             output_path: name,
@@ -177,6 +361,7 @@ impl Commands {
             border_padding,
             ..Default::default()
         };
+        packing.apply(&mut config);
 
         let json = serde_json::to_string_pretty(&config)?;
         let mut file = std::fs::File::create(format!("{}.rpack_gen.json", config_path)).unwrap();
@@ -185,8 +370,50 @@ impl Commands {
         Ok(())
     }
 
-    fn generate_tilemap_from_config(config_path: String) -> anyhow::Result<()> {
+    fn generate_tilemap_from_config(config_path: String, force: bool) -> anyhow::Result<()> {
         let config = TilemapGenerationConfig::read_from_file(config_path)?;
-        config.generate()
+        if force {
+            config.force_generate()
+        } else {
+            config.generate()
+        }
+    }
+}
+
+/// Returns the path of the image extracted from the frame with given key.
+///
+/// Keys come from the atlas file, so the ones leaving `output_dir` are rejected.
+fn frame_output_path(output_dir: &Path, key: &str) -> anyhow::Result<PathBuf> {
+    let relative = Path::new(key);
+    if key.is_empty()
+        || !relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+    {
+        anyhow::bail!("frame key {key:?} is not a relative path inside the output directory");
+    }
+    Ok(output_dir.join(format!("{key}.png")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_output_path_rejects_keys_leaving_output_dir() {
+        let output_dir = Path::new("out");
+        assert_eq!(
+            frame_output_path(output_dir, "ui/button").unwrap(),
+            output_dir.join("ui/button.png")
+        );
+        for key in [
+            "",
+            "../escape",
+            "ui/../../escape",
+            "/etc/escape",
+            "./ui/button",
+        ] {
+            assert!(frame_output_path(output_dir, key).is_err(), "{key}");
+        }
     }
 }
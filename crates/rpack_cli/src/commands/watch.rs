@@ -0,0 +1,206 @@
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use notify_debouncer_full::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_full::{Debouncer, RecommendedCache, new_debouncer};
+use rpack_cli::cache::{CACHE_EXTENSION, GenerationCache};
+use rpack_cli::{SIDECAR_EXTENSIONS, TilemapGenerationConfig};
+
+/// Config file and the directories with its sources.
+struct WatchedConfig {
+    path: PathBuf,
+    /// Last successfully read config, kept for matching changes while the file is invalid.
+    config: Option<TilemapGenerationConfig>,
+    /// Files written by the last generation, including its cache file.
+    outputs: Vec<PathBuf>,
+}
+
+impl WatchedConfig {
+    fn reload(&mut self) -> anyhow::Result<&TilemapGenerationConfig> {
+        let config = TilemapGenerationConfig::read_from_file(&self.path)?;
+        Ok(self.config.insert(config))
+    }
+
+    /// Reloads the config and generates the tilemap, errors and panics are reported without stopping.
+    fn rebuild(&mut self, changed: &[&Path]) {
+        match changed {
+            [] => println!("Generating {}", self.path.display()),
+            [path] => println!("{} changed, regenerating", path.display()),
+            paths => println!("{} files changed, regenerating", paths.len()),
+        }
+        let start = Instant::now();
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            self.reload().and_then(TilemapGenerationConfig::generate)
+        }));
+        match result {
+            Ok(Ok(())) => println!("Done in {:.2?}", start.elapsed()),
+            Ok(Err(e)) => eprintln!("Failed to generate {}: {e:#}", self.path.display()),
+            Err(_) => eprintln!("Failed to generate {}", self.path.display()),
+        }
+        self.read_outputs();
+    }
+
+    /// Reads the files written by the last generation from its cache file.
+    fn read_outputs(&mut self) {
+        let Some(config) = &self.config else {
+            return;
+        };
+        let working_dir = config.working_dir();
+        let cache_path = working_dir.join(format!("{}.{CACHE_EXTENSION}", config.output_path));
+        let Some(cache) = GenerationCache::read(&cache_path) else {
+            return;
+        };
+        self.outputs = cache
+            .outputs
+            .iter()
+            .chain([&cache_path])
+            .filter_map(|path| std::path::absolute(path).ok())
+            .collect();
+    }
+
+    /// Directory of the config and the directories before the first wildcard of every asset pattern.
+    fn watched_dirs(&self) -> Vec<(PathBuf, RecursiveMode)> {
+        let mut dirs: Vec<(PathBuf, RecursiveMode)> = Vec::new();
+        if let Some(config) = &self.config {
+            let working_dir = config.working_dir();
+            for pattern in config.asset_patterns.iter() {
+                let root = pattern_root(&working_dir, pattern);
+                if !dirs.iter().any(|(dir, _)| root.starts_with(dir)) {
+                    dirs.retain(|(dir, _)| !dir.starts_with(&root));
+                    dirs.push((root, RecursiveMode::Recursive));
+                }
+            }
+        }
+        if let Some(parent) = self.path.parent()
+            && !dirs.iter().any(|(dir, _)| parent.starts_with(dir))
+        {
+            dirs.push((parent.to_path_buf(), RecursiveMode::NonRecursive));
+        }
+        dirs
+    }
+
+    /// Returns `true` if the change at `path` can affect the generated tilemap.
+    fn is_affected_by(&self, path: &Path) -> bool {
+        if path == self.path {
+            return true;
+        }
+        let Some(config) = &self.config else {
+            return false;
+        };
+        if self.outputs.iter().any(|output| output == path) {
+            return false;
+        }
+        let working_dir = config.working_dir();
+        let lossy_working_dir = working_dir.to_string_lossy();
+        let matches_pattern = config.asset_patterns.iter().any(|pattern| {
+            glob::Pattern::new(&format!("{}/{}", lossy_working_dir, pattern))
+                .is_ok_and(|p| p.matches_path(path))
+        });
+        let is_sidecar = SIDECAR_EXTENSIONS
+            .iter()
+            .any(|extension| path.to_string_lossy().ends_with(extension));
+        // Directories are reported when they are created, removed or renamed with all images inside
+        let is_dir = path.is_dir() || path.extension().is_none();
+        (matches_pattern || is_sidecar || is_dir)
+            && self
+                .watched_dirs()
+                .iter()
+                .any(|(dir, _)| path.starts_with(dir))
+    }
+}
+
+/// Longest path of `pattern` without wildcards, limited to existing directories.
+fn pattern_root(working_dir: &Path, pattern: &str) -> PathBuf {
+    let mut root = working_dir.to_path_buf();
+    for component in Path::new(pattern).components() {
+        if component
+            .as_os_str()
+            .to_string_lossy()
+            .contains(['*', '?', '[', '{'])
+        {
+            break;
+        }
+        root.push(component);
+    }
+    while !root.is_dir() && root.pop() {}
+    root
+}
+
+/// Generates tilemaps from the configs at `config_paths` and regenerates them after their sources change,
+/// waiting `debounce` for more changes first.
+pub(crate) fn watch(config_paths: &[PathBuf], debounce: Duration) -> anyhow::Result<()> {
+    let mut configs = config_paths
+        .iter()
+        .map(|path| {
+            Ok(WatchedConfig {
+                path: std::path::absolute(path)?,
+                config: None,
+                outputs: Vec::new(),
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let (tx, rx) = mpsc::channel();
+    let mut debouncer = new_debouncer(debounce, None, tx)?;
+    let mut watched = Vec::new();
+    for config in configs.iter_mut() {
+        config.rebuild(&[]);
+        watch_dirs(config, &mut debouncer, &mut watched);
+    }
+    println!("Watching for changes, press Ctrl+C to stop");
+
+    for result in rx {
+        let events = match result {
+            Ok(events) => events,
+            Err(errors) => {
+                for e in errors {
+                    eprintln!("Watch error: {e}");
+                }
+                continue;
+            }
+        };
+        // Files are opened during generation, only modifications are relevant
+        let paths: Vec<&Path> = events
+            .iter()
+            .filter(|event| !event.kind.is_access())
+            .flat_map(|event| event.paths.iter().map(PathBuf::as_path))
+            .collect();
+        for config in configs.iter_mut() {
+            let mut changed: Vec<&Path> = paths
+                .iter()
+                .copied()
+                .filter(|path| config.is_affected_by(path))
+                .collect();
+            changed.sort();
+            changed.dedup();
+            if changed.is_empty() {
+                continue;
+            }
+            config.rebuild(&changed);
+            watch_dirs(config, &mut debouncer, &mut watched);
+        }
+    }
+    Ok(())
+}
+
+/// Watches directories of `config` not covered by the already `watched` ones.
+fn watch_dirs(
+    config: &WatchedConfig,
+    debouncer: &mut Debouncer<RecommendedWatcher, RecommendedCache>,
+    watched: &mut Vec<(PathBuf, RecursiveMode)>,
+) {
+    for (dir, mode) in config.watched_dirs() {
+        let covered = watched.iter().any(|(watched_dir, watched_mode)| {
+            watched_dir == &dir
+                || (*watched_mode == RecursiveMode::Recursive && dir.starts_with(watched_dir))
+        });
+        if covered {
+            continue;
+        }
+        match debouncer.watch(&dir, mode) {
+            Ok(()) => watched.push((dir, mode)),
+            Err(e) => eprintln!("Failed to watch {}: {e}", dir.display()),
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::fmt::Write;
+
+use bevy_rpack::{AnimationLoopMode, AtlasAnimation, AtlasAsset, AtlasFrame};
+
+use super::ExportedFile;
+use crate::SpritesheetError;
+
+/// Writes an `AtlasTexture` per frame and a `SpriteFrames` resource when the atlas has animations.
+pub(super) fn export(
+    atlas: &AtlasAsset,
+    name: &str,
+) -> Result<Vec<ExportedFile>, SpritesheetError> {
+    if let Some(frame) = atlas.frames.iter().find(|f| f.rotated) {
+        return Err(SpritesheetError::ExportError(format!(
+            "Godot AtlasTexture does not support rotated frames, {} is rotated",
+            frame.key
+        )));
+    }
+    let mut files = atlas_textures(atlas, name);
+    if !atlas.animations.is_empty() {
+        files.push(sprite_frames(atlas, name));
+    }
+    Ok(files)
+}
+
+/// One resource per frame placed in the `{name}_frames` directory, mirroring the frame keys.
+fn atlas_textures(atlas: &AtlasAsset, name: &str) -> Vec<ExportedFile> {
+    let pages = atlas.pages();
+    atlas
+        .frames
+        .iter()
+        .map(|frame| {
+            // Paths of external resources are relative to the resource file
+            let depth = frame.key.matches('/').count() + 1;
+            let image_path = format!("{}{}", "../".repeat(depth), pages[frame.page].filename);
+            let mut content = String::new();
+            let _ = writeln!(
+                content,
+                "[gd_resource type=\"AtlasTexture\" load_steps=2 format=3]\n"
+            );
+            let _ = writeln!(
+                content,
+                "[ext_resource type=\"Texture2D\" path=\"{image_path}\" id=\"1\"]\n"
+            );
+            let _ = writeln!(content, "[resource]");
+            write_atlas_texture(&mut content, frame, "1");
+            ExportedFile {
+                filename: format!("{name}_frames/{}.tres", frame.key),
+                content,
+            }
+        })
+        .collect()
+}
+
+/// Name, frame keys with durations in milliseconds and whether the animation loops.
+type GodotAnimation<'a> = (&'a String, Vec<(&'a str, f32)>, bool);
+
+/// Single resource with every animation, frames are embedded as `AtlasTexture` sub-resources.
+fn sprite_frames(atlas: &AtlasAsset, name: &str) -> ExportedFile {
+    let pages = atlas.pages();
+    let animations: Vec<GodotAnimation> = atlas
+        .animations
+        .iter()
+        .map(|(name, animation)| {
+            let (frames, looped) = godot_frames(animation);
+            (name, frames, looped)
+        })
+        .collect();
+    let mut used: Vec<&AtlasFrame> = Vec::new();
+    for (_, frames, _) in animations.iter() {
+        for (key, _) in frames {
+            if !used.iter().any(|f| f.key == *key)
+                && let Some(frame) = atlas.frames.iter().find(|f| f.key == *key)
+            {
+                used.push(frame);
+            }
+        }
+    }
+
+    let mut content = String::new();
+    let _ = writeln!(
+        content,
+        "[gd_resource type=\"SpriteFrames\" load_steps={} format=3]\n",
+        pages.len() + used.len() + 1
+    );
+    for (i, page) in pages.iter().enumerate() {
+        let _ = writeln!(
+            content,
+            "[ext_resource type=\"Texture2D\" path=\"{}\" id=\"{}\"]",
+            page.filename,
+            i + 1
+        );
+    }
+    for (i, frame) in used.iter().enumerate() {
+        let _ = writeln!(
+            content,
+            "\n[sub_resource type=\"AtlasTexture\" id=\"AtlasTexture_{i}\"]"
+        );
+        write_atlas_texture(&mut content, frame, &(frame.page + 1).to_string());
+    }
+    let _ = writeln!(content, "\n[resource]");
+    let animations: Vec<String> = animations
+        .iter()
+        .map(|(name, frames, looped)| {
+            // Godot durations are relative to the speed in frames per second
+            let base = frames.first().map_or(1.0, |(_, d)| d.max(1.0));
+            let frames: Vec<String> = frames
+                .iter()
+                .filter_map(|(key, duration)| {
+                    let i = used.iter().position(|f| f.key == *key)?;
+                    Some(format!(
+                        "{{\n\"duration\": {:?},\n\"texture\": SubResource(\"AtlasTexture_{i}\")\n}}",
+                        duration / base
+                    ))
+                })
+                .collect();
+            format!(
+                "{{\n\"frames\": [{}],\n\"loop\": {looped},\n\"name\": &\"{name}\",\n\"speed\": {:?}\n}}",
+                frames.join(", "),
+                1000.0 / base
+            )
+        })
+        .collect();
+    let _ = writeln!(content, "animations = [{}]", animations.join(", "));
+    ExportedFile {
+        filename: format!("{name}.sprite_frames.tres"),
+        content,
+    }
+}
+
+/// Frame keys with durations in milliseconds and whether the animation loops.
+///
+/// Godot has no ping-pong mode, so the frames played backwards are appended.
+fn godot_frames(animation: &AtlasAnimation) -> (Vec<(&str, f32)>, bool) {
+    let mut frames: Vec<(&str, f32)> = animation
+        .frames
+        .iter()
+        .enumerate()
+        .map(|(i, key)| (key.as_str(), animation.duration(i) as f32))
+        .collect();
+    match animation.loop_mode {
+        AnimationLoopMode::Loop => (frames, true),
+        AnimationLoopMode::Once => (frames, false),
+        AnimationLoopMode::PingPong => {
+            let len = frames.len();
+            if len > 2 {
+                let backwards: Vec<(&str, f32)> =
+                    frames[1..len - 1].iter().rev().copied().collect();
+                frames.extend(backwards);
+            }
+            (frames, true)
+        }
+    }
+}
+
+fn write_atlas_texture(content: &mut String, frame: &AtlasFrame, texture_id: &str) {
+    let rect = frame.frame;
+    let _ = writeln!(content, "atlas = ExtResource(\"{texture_id}\")");
+    let _ = writeln!(
+        content,
+        "region = Rect2({}, {}, {}, {})",
+        rect.x, rect.y, rect.w, rect.h
+    );
+    if let Some(trim) = frame.trim {
+        // Margin restores the transparent area removed by trimming
+        let _ = writeln!(
+            content,
+            "margin = Rect2({}, {}, {}, {})",
+            trim.offset[0],
+            trim.offset[1],
+            trim.source_size[0] - rect.w,
+            trim.source_size[1] - rect.h
+        );
+    }
+    let _ = writeln!(content, "filter_clip = true");
+}
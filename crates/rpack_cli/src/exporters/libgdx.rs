@@ -0,0 +1,131 @@
+use std::fmt::Write;
+
+use bevy_rpack::{AtlasAsset, AtlasFrame};
+
+use super::ExportedFile;
+use crate::SpritesheetError;
+use crate::animations::sequence_frame;
+
+/// Writes a single `.atlas` file describing all pages.
+///
+/// Frames rotated counter-clockwise are written as `rotate: true`, frames rotated clockwise as `rotate: 270`,
+/// which is read by Spine but not by the libGDX `TextureAtlas`.
+pub(super) fn export(atlas: &AtlasAsset, name: &str) -> Result<ExportedFile, SpritesheetError> {
+    let mut content = String::new();
+    for (i, page) in atlas.pages().iter().enumerate() {
+        if i > 0 {
+            content.push('\n');
+        }
+        let _ = writeln!(content, "{}", page.filename);
+        let _ = writeln!(content, "size: {}, {}", page.size[0], page.size[1]);
+        let _ = writeln!(content, "format: RGBA8888");
+        let _ = writeln!(content, "filter: Nearest, Nearest");
+        let _ = writeln!(content, "repeat: none");
+        for frame in atlas.frames.iter().filter(|f| f.page == i) {
+            write_region(&mut content, atlas, frame);
+        }
+    }
+    Ok(ExportedFile {
+        filename: format!("{name}.atlas"),
+        content,
+    })
+}
+
+fn write_region(content: &mut String, atlas: &AtlasAsset, frame: &AtlasFrame) {
+    // Frames of numbered sequences share the name and are told apart by the index
+    let sequence = sequence_frame(&frame.key).filter(|(name, _)| {
+        atlas
+            .animations
+            .get(*name)
+            .is_some_and(|a| a.frames.contains(&frame.key))
+    });
+    let [w, h] = frame.size();
+    match sequence {
+        Some((name, index)) => {
+            let _ = writeln!(content, "{name}");
+            let _ = writeln!(content, "  index: {index}");
+        }
+        None => {
+            let _ = writeln!(content, "{}", frame.key);
+        }
+    }
+    let _ = writeln!(
+        content,
+        "  bounds: {}, {}, {}, {}",
+        frame.frame.x, frame.frame.y, w, h
+    );
+    match (frame.rotated, frame.counter_clockwise) {
+        (true, true) => {
+            let _ = writeln!(content, "  rotate: true");
+        }
+        (true, false) => {
+            let _ = writeln!(content, "  rotate: 270");
+        }
+        (false, _) => {}
+    }
+    if let Some(trim) = frame.trim {
+        // Offsets are measured from the bottom-left corner of the original image
+        let bottom = trim.source_size[1] - trim.offset[1] - h;
+        let _ = writeln!(
+            content,
+            "  offsets: {}, {}, {}, {}",
+            trim.offset[0], bottom, trim.source_size[0], trim.source_size[1]
+        );
+    }
+    if let Some(slice) = frame.slice {
+        let _ = writeln!(
+            content,
+            "  split: {}, {}, {}, {}",
+            slice.left, slice.right, slice.top, slice.bottom
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, Rgba, RgbaImage};
+    use texture_packer::TexturePackerConfig;
+
+    use crate::compositing::extract_frame;
+    use crate::{ImageFile, Spritesheet, SpritesheetBuildConfig};
+
+    #[test]
+    fn rotated_frames_round_trip_through_libgdx_atlas() {
+        // Only fits the page when rotated, every pixel differs to catch a wrong orientation
+        let source = DynamicImage::ImageRgba8(RgbaImage::from_fn(8, 2, |x, y| {
+            Rgba([x as u8 * 16, y as u8 * 64, 0, 255])
+        }));
+        let image = ImageFile {
+            id: "wide".to_owned(),
+            image: source.clone(),
+        };
+        for counter_clockwise in [true, false] {
+            let config = SpritesheetBuildConfig {
+                counter_clockwise,
+                ..TexturePackerConfig {
+                    max_width: 4,
+                    max_height: 8,
+                    allow_rotation: true,
+                    border_padding: 0,
+                    texture_padding: 0,
+                    ..Default::default()
+                }
+                .into()
+            };
+            let spritesheet =
+                Spritesheet::build(config, std::slice::from_ref(&image), "atlas.png").unwrap();
+            let frame = &spritesheet.atlas_asset.frames[0];
+            assert!(frame.rotated);
+            assert_eq!(frame.counter_clockwise, counter_clockwise);
+
+            let exported = super::export(&spritesheet.atlas_asset, "atlas").unwrap();
+            let rotate = if counter_clockwise { "true" } else { "270" };
+            assert!(exported.content.contains(&format!("  rotate: {rotate}\n")));
+            assert!(!exported.content.contains("pad:"));
+            let imported = bevy_rpack::import::from_libgdx_atlas(&exported.content).unwrap();
+            assert_eq!(imported.frames[0].frame, frame.frame);
+            let extracted = extract_frame(&spritesheet.image_data, &imported.frames[0]);
+            assert_eq!(extracted.to_rgba8(), source.to_rgba8());
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use std::fmt::Display;
+
+use bevy_rpack::AtlasAsset;
+use serde::{Deserialize, Serialize};
+
+use crate::SpritesheetError;
+
+mod godot;
+mod libgdx;
+mod texture_packer;
+
+/// Additional atlas description formats written next to the `.rpack.json` file.
+#[derive(Clone, Debug, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(
+    all(feature = "cli", not(target_arch = "wasm32")),
+    derive(clap::ValueEnum)
+)]
+pub enum ExportFormat {
+    /// TexturePacker JSON with frames stored in an object keyed by their names.
+    TexturePackerHash,
+    /// TexturePacker JSON with frames stored in an array.
+    TexturePackerArray,
+    /// libGDX and Spine `.atlas` text format, requesting it packs rotated images counter-clockwise as libGDX expects.
+    #[cfg_attr(
+        all(feature = "cli", not(target_arch = "wasm32")),
+        value(name = "libgdx")
+    )]
+    LibGdx,
+    /// Godot `AtlasTexture` resource per frame and a `SpriteFrames` resource when the atlas has animations.
+    Godot,
+}
+
+/// File created by an [`ExportFormat`], `filename` is relative to the directory of the atlas images.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExportedFile {
+    pub filename: String,
+    pub content: String,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 4] = [
+        ExportFormat::TexturePackerHash,
+        ExportFormat::TexturePackerArray,
+        ExportFormat::LibGdx,
+        ExportFormat::Godot,
+    ];
+
+    /// Describes the `atlas` in this format, `name` is the atlas name without an extension.
+    pub fn export(
+        &self,
+        atlas: &AtlasAsset,
+        name: &str,
+    ) -> Result<Vec<ExportedFile>, SpritesheetError> {
+        match self {
+            ExportFormat::TexturePackerHash => texture_packer::export(atlas, name, false),
+            ExportFormat::TexturePackerArray => texture_packer::export(atlas, name, true),
+            ExportFormat::LibGdx => Ok(vec![libgdx::export(atlas, name)?]),
+            ExportFormat::Godot => godot::export(atlas, name),
+        }
+    }
+}
+
+impl Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportFormat::TexturePackerHash => f.write_str("TexturePacker JSON (Hash)"),
+            ExportFormat::TexturePackerArray => f.write_str("TexturePacker JSON (Array)"),
+            ExportFormat::LibGdx => f.write_str("libGDX Atlas"),
+            ExportFormat::Godot => f.write_str("Godot Resources"),
+        }
+    }
+}
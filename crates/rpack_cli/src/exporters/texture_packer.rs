@@ -0,0 +1,155 @@
+use bevy_rpack::{AtlasAsset, AtlasFrame};
+use serde_json::{Map, Value, json};
+
+use super::ExportedFile;
+use crate::SpritesheetError;
+
+/// Name of the description of the given page, pages get a suffix only in multi-page atlases.
+///
+/// The `.tp.json` and `.tp.array.json` extensions are loaded by the `bevy_rpack` atlas loader.
+fn page_description_filename(name: &str, page: Option<usize>, array: bool) -> String {
+    let extension = if array { "tp.array.json" } else { "tp.json" };
+    match page {
+        Some(page) => format!("{name}_{page}.{extension}"),
+        None => format!("{name}.{extension}"),
+    }
+}
+
+/// Writes one description per atlas page, multi-page atlases list the others in `related_multi_packs`.
+pub(super) fn export(
+    atlas: &AtlasAsset,
+    name: &str,
+    array: bool,
+) -> Result<Vec<ExportedFile>, SpritesheetError> {
+    let pages = atlas.pages();
+    let multi_page = pages.len() > 1;
+    let filenames: Vec<String> = (0..pages.len())
+        .map(|i| page_description_filename(name, multi_page.then_some(i), array))
+        .collect();
+    let mut files = Vec::with_capacity(pages.len());
+    for (i, page) in pages.iter().enumerate() {
+        let frames = atlas.frames.iter().filter(|f| f.page == i);
+        let frames = if array {
+            Value::Array(
+                frames
+                    .map(|f| {
+                        let mut frame = frame_json(f);
+                        frame["filename"] = json!(f.key);
+                        frame
+                    })
+                    .collect(),
+            )
+        } else {
+            Value::Object(frames.map(|f| (f.key.clone(), frame_json(f))).collect())
+        };
+        let mut meta = json!({
+            "app": atlas.metadata.app,
+            "version": atlas.metadata.app_version,
+            "image": page.filename,
+            "format": "RGBA8888",
+            "size": { "w": page.size[0], "h": page.size[1] },
+            "scale": "1",
+        });
+        if multi_page {
+            meta["related_multi_packs"] = filenames
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, f)| json!(f))
+                .collect();
+        }
+        let mut description = json!({ "frames": frames, "meta": meta });
+        // Animations reference frames from all pages, so they are written once
+        if i == 0 && !atlas.animations.is_empty() {
+            description["animations"] = Value::Object(
+                atlas
+                    .animations
+                    .iter()
+                    .map(|(name, animation)| (name.clone(), json!(animation.frames)))
+                    .collect::<Map<String, Value>>(),
+            );
+        }
+        files.push(ExportedFile {
+            filename: filenames[i].clone(),
+            content: serde_json::to_string_pretty(&description)
+                .map_err(|e| SpritesheetError::ParsingError(e.to_string()))?,
+        });
+    }
+    Ok(files)
+}
+
+/// Frame entry, `frame` size is the size before rotation like in TexturePacker.
+fn frame_json(frame: &AtlasFrame) -> Value {
+    let [w, h] = frame.size();
+    let (sprite_source_size, source_size) = match frame.trim {
+        Some(trim) => (
+            json!({ "x": trim.offset[0], "y": trim.offset[1], "w": w, "h": h }),
+            json!({ "w": trim.source_size[0], "h": trim.source_size[1] }),
+        ),
+        None => (
+            json!({ "x": 0, "y": 0, "w": w, "h": h }),
+            json!({ "w": w, "h": h }),
+        ),
+    };
+    let mut value = json!({
+        "frame": { "x": frame.frame.x, "y": frame.frame.y, "w": w, "h": h },
+        "rotated": frame.rotated,
+        "trimmed": frame.trim.is_some(),
+        "spriteSourceSize": sprite_source_size,
+        "sourceSize": source_size,
+    });
+    if let Some([x, y]) = frame.pivot {
+        value["pivot"] = json!({ "x": x, "y": y });
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, RgbaImage};
+    use texture_packer::TexturePackerConfig;
+
+    use crate::{ImageFile, Spritesheet};
+
+    #[test]
+    fn exported_descriptions_import_back() {
+        let images: Vec<ImageFile> = [("a", 8, 4), ("b", 4, 4), ("c", 2, 6)]
+            .into_iter()
+            .map(|(id, w, h)| ImageFile {
+                id: id.to_owned(),
+                image: DynamicImage::ImageRgba8(RgbaImage::new(w, h)),
+            })
+            .collect();
+        let config = TexturePackerConfig {
+            max_width: 32,
+            max_height: 32,
+            ..Default::default()
+        };
+        let atlas = Spritesheet::build(config, &images, "atlas.png")
+            .unwrap()
+            .atlas_asset;
+        for (array, filename) in [(false, "atlas.tp.json"), (true, "atlas.tp.array.json")] {
+            let [file] = super::export(&atlas, "atlas", array)
+                .unwrap()
+                .try_into()
+                .unwrap();
+            assert_eq!(file.filename, filename);
+            let imported =
+                bevy_rpack::import::from_file_content(&file.filename, &file.content).unwrap();
+            let pages = |a: &bevy_rpack::AtlasAsset| {
+                a.pages()
+                    .iter()
+                    .map(|p| (p.filename.clone(), p.size))
+                    .collect::<Vec<_>>()
+            };
+            let frames = |a: &bevy_rpack::AtlasAsset| {
+                a.frames
+                    .iter()
+                    .map(|f| (f.key.clone(), f.frame, f.page, f.rotated))
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(pages(&imported), pages(&atlas));
+            assert_eq!(frames(&imported), frames(&atlas));
+        }
+    }
+}
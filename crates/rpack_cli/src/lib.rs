@@ -1,58 +1,57 @@
-use bevy_rpack::{AtlasFrame, AtlasMetadata, SerializableRect};
-use image::DynamicImage;
+#[cfg(all(feature = "config_ext", not(target_arch = "wasm32")))]
+use bevy_rpack::AtlasFrameSlice;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 #[cfg(all(feature = "config_ext", not(target_arch = "wasm32")))]
 use std::io::Write;
 use std::{
+    collections::HashMap,
     ffi::OsStr,
     path::{Path, PathBuf},
 };
-use texture_packer::{TexturePacker, TexturePackerConfig, importer::ImageImporter};
-use thiserror::Error;
+use texture_packer::TexturePackerConfig;
+
+use crate::packer::AutoSizeConfig;
 
+pub use crate::animations::AnimationConfig;
+pub use crate::compositing::ExtrusionFill;
+pub use crate::exporters::ExportFormat;
 pub use crate::formats::SaveImageFormat;
+pub use crate::packer::{PackingAlgorithm, SortHeuristic};
+pub use bevy_rpack::generation::{NineSliceConfig, PivotConfig};
+pub use bevy_rpack::spritesheet::{
+    DeduplicationSummary, ImageFile, Spritesheet, SpritesheetBuildConfig, SpritesheetError,
+    page_filename,
+};
+pub use bevy_rpack::{compositing, packer};
 
+pub mod animations;
+#[cfg(all(feature = "build", not(target_arch = "wasm32")))]
+pub mod build;
+#[cfg(all(feature = "config_ext", not(target_arch = "wasm32")))]
+pub mod cache;
+pub mod codegen;
+pub mod exporters;
 pub mod formats;
-pub mod packer;
 pub mod saving;
 
-#[derive(Clone)]
-pub struct Spritesheet {
-    pub image_data: DynamicImage,
-    pub atlas_asset: bevy_rpack::AtlasAsset,
-    pub atlas_asset_json: Value,
-}
+/// Reads the file next to the image at `path` with its extension replaced by `extension`, `None` if it does not exist.
+#[cfg(all(feature = "config_ext", not(target_arch = "wasm32")))]
+fn read_sidecar<T: serde::de::DeserializeOwned>(
+    path: Option<&Path>,
+    extension: &str,
+) -> anyhow::Result<Option<T>> {
+    use anyhow::Context;
 
-impl Spritesheet {
-    pub fn rebuild_json(&mut self) {
-        if let Ok(value) = serde_json::to_value(&self.atlas_asset) {
-            self.atlas_asset_json = value;
-        }
-    }
-}
-
-#[derive(Clone, PartialEq)]
-pub struct ImageFile {
-    pub id: String,
-    pub image: DynamicImage,
-}
-
-impl ImageFile {
-    pub fn at_path<P>(path: &Path, id: P) -> Option<ImageFile>
-    where
-        P: AsRef<str>,
-    {
-        let mut id = id.as_ref().to_owned().replace("\\", "/");
-        if let Some((before, _)) = id.split_once('.') {
-            id = before.to_string();
-        }
-        if let Ok(image) = ImageImporter::import_from_file(path) {
-            Some(ImageFile { image, id })
-        } else {
-            None
-        }
+    let Some(sidecar) = path.map(|p| p.with_extension(extension)) else {
+        return Ok(None);
+    };
+    if !sidecar.is_file() {
+        return Ok(None);
     }
+    let content = std::fs::read_to_string(&sidecar)?;
+    let value = serde_json::from_str(&content)
+        .with_context(|| format!("Invalid sidecar file: {}", sidecar.display()))?;
+    Ok(Some(value))
 }
 
 pub fn get_common_prefix<S>(paths: &[S]) -> String
@@ -92,99 +91,6 @@ where
     prefix
 }
 
-/// Errors that can occur while building a `Spritesheet`.
-#[non_exhaustive]
-#[derive(Debug, Error, Clone)]
-pub enum SpritesheetError {
-    #[error("Cannot pack image: {0}")]
-    CannotPackImage(String),
-    #[error("Failed to export tilemap image")]
-    FailedToExportImage,
-    #[error("could not parse asset: {0}")]
-    ParsingError(String),
-    #[error("Failed to pack image into tilemap, tilemap to small")]
-    FailedToPackImage,
-}
-
-/// Configuration for building a `Spritesheet`.
-#[derive(Debug, Clone)]
-pub struct SpritesheetBuildConfig {
-    /// Configuration for the texture packer.
-    pub packer_config: TexturePackerConfig,
-    /// Whether to skip metadata serialization.
-    pub skip_metadata_serialization: bool,
-}
-
-impl From<TexturePackerConfig> for SpritesheetBuildConfig {
-    fn from(config: TexturePackerConfig) -> Self {
-        Self {
-            packer_config: config,
-            skip_metadata_serialization: false,
-        }
-    }
-}
-
-impl Spritesheet {
-    pub fn build<P>(
-        config: impl Into<SpritesheetBuildConfig>,
-        images: &[ImageFile],
-        filename: P,
-    ) -> Result<Self, SpritesheetError>
-    where
-        P: AsRef<str>,
-    {
-        let SpritesheetBuildConfig {
-            packer_config: config,
-            skip_metadata_serialization,
-        } = config.into();
-        let mut packer = TexturePacker::new_skyline(config);
-        for image in images.iter() {
-            if !packer.can_pack(&image.image) {
-                return Err(SpritesheetError::CannotPackImage(image.id.clone()));
-            }
-            if let Err(_err) = packer.pack_ref(&image.id, &image.image) {
-                return Err(SpritesheetError::FailedToPackImage);
-            }
-        }
-        let Ok(image_data) = texture_packer::exporter::ImageExporter::export(&packer, None) else {
-            return Err(SpritesheetError::FailedToExportImage);
-        };
-
-        let mut atlas_asset = bevy_rpack::AtlasAsset {
-            metadata: AtlasMetadata {
-                skip_serialization: skip_metadata_serialization,
-                ..Default::default()
-            },
-            size: [image_data.width(), image_data.height()],
-            filename: filename.as_ref().to_owned(),
-            frames: packer
-                .get_frames()
-                .values()
-                .map(|v| -> AtlasFrame {
-                    AtlasFrame {
-                        key: v.key.clone(),
-                        frame: SerializableRect {
-                            x: v.frame.x,
-                            y: v.frame.y,
-                            w: v.frame.w,
-                            h: v.frame.h,
-                        },
-                    }
-                })
-                .collect(),
-        };
-        atlas_asset.frames.sort_by(|a, b| a.key.cmp(&b.key));
-        let atlas_asset_json = serde_json::to_value(&atlas_asset)
-            .map_err(|e| SpritesheetError::ParsingError(e.to_string()))?;
-
-        Ok(Spritesheet {
-            image_data,
-            atlas_asset,
-            atlas_asset_json,
-        })
-    }
-}
-
 #[derive(Clone, Serialize, Deserialize, Default, PartialEq)]
 pub struct TilemapGenerationConfig {
     pub asset_patterns: Vec<String>,
@@ -192,17 +98,84 @@ pub struct TilemapGenerationConfig {
     /// Image format, png by default
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub format: Option<SaveImageFormat>,
-    /// Size of the tilemap texture. Default value is `2048`.
+    /// Size of the tilemap texture, the upper bound when `auto_size` is enabled. Default value is `2048`.
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub size: Option<u32>,
+    /// Width of the tilemap texture overriding `size`, the upper bound of the width when `auto_size` is enabled.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_width: Option<u32>,
+    /// Height of the tilemap texture overriding `size`, the upper bound of the height when `auto_size` is enabled.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_height: Option<u32>,
+    /// Shrinks the tilemap texture to the smallest size fitting all images. Default value is `false`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub auto_size: Option<bool>,
+    /// Limits the automatic size to powers of two. Default value is `true`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub power_of_two: Option<bool>,
+    /// Allows the automatic size to have different width and height. Default value is `false`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub allow_non_square: Option<bool>,
     /// Size of the padding between frames in pixel. Default value is `2`
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub texture_padding: Option<u32>,
     /// Size of the padding on the outer edge of the packed image in pixel. Default value is `0`.
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub border_padding: Option<u32>,
+    /// Size of the area around each frame filled according to `extrusion_fill` in pixel. Default value is `0`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub texture_extrusion: Option<u32>,
+    /// Defines how the extrusion area is filled. Default value is `EdgeRepeat`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub extrusion_fill: Option<ExtrusionFill>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub skip_serializing_metadata: Option<bool>,
+    /// Places images that do not fit into a single texture on additional pages. Default value is `false`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub allow_multiple_pages: Option<bool>,
+    /// Removes transparent borders of images before packing. Default value is `false`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub trim: Option<bool>,
+    /// Pixels with alpha lower or equal to this value are treated as transparent during trimming. Default value is `0`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub trim_alpha_threshold: Option<u8>,
+    /// Algorithm used for placing images in the atlas. Default value is `Skyline`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub packing_algorithm: Option<PackingAlgorithm>,
+    /// Order in which images are packed. Default value is `None`, keeping the sorted file paths order.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub sort_heuristic: Option<SortHeuristic>,
+    /// Packs pixel-identical images once, all their keys point at the same area. Default value is `false`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub deduplicate: Option<bool>,
+    /// Nine-slice borders of images with keys matching the patterns, first matching pattern is used.
+    ///
+    /// Borders from a `.slice.json` file next to the image take precedence.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub nine_slices: Vec<NineSliceConfig>,
+    /// Pivot points of images with keys matching the patterns, first matching pattern is used.
+    ///
+    /// Pivot from a `.pivot.json` file next to the image takes precedence.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub pivots: Vec<PivotConfig>,
+    /// Groups keys ending with a frame number, like `run_000`, into animations. Default value is `false`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub detect_animations: Option<bool>,
+    /// Duration of animation frames in milliseconds. Default value is `100`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub animation_frame_duration: Option<u32>,
+    /// Explicitly defined animations, replacing detected ones with the same name.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub animations: Vec<AnimationConfig>,
+    /// Allows rotating images by 90 degrees when it lets them fit better. Default value is `false`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub allow_rotation: Option<bool>,
+    /// Additional atlas descriptions written next to the `.rpack.json` file.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub export_formats: Vec<ExportFormat>,
+    /// Path of a Rust file with constants for all frame keys and animations, relative to the config.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub rust_module: Option<String>,
     #[serde(skip)]
     pub working_dir: Option<PathBuf>,
 }
@@ -210,13 +183,14 @@ pub struct TilemapGenerationConfig {
 impl From<&TilemapGenerationConfig> for TexturePackerConfig {
     fn from(config: &TilemapGenerationConfig) -> Self {
         texture_packer::TexturePackerConfig {
-            max_width: config.size.unwrap_or(2048),
-            max_height: config.size.unwrap_or(2048),
-            allow_rotation: false,
+            max_width: config.max_width.or(config.size).unwrap_or(2048),
+            max_height: config.max_height.or(config.size).unwrap_or(2048),
+            allow_rotation: config.allow_rotation.unwrap_or_default(),
             force_max_dimensions: true,
             border_padding: config.border_padding.unwrap_or(0),
             texture_padding: config.texture_padding.unwrap_or(2),
-            texture_extrusion: 0,
+            texture_extrusion: config.texture_extrusion.unwrap_or(0),
+            // Trimming is done by `Spritesheet::build` to support alpha threshold and record offsets.
             trim: false,
             texture_outlines: false,
         }
@@ -227,10 +201,37 @@ impl From<&TilemapGenerationConfig> for SpritesheetBuildConfig {
         SpritesheetBuildConfig {
             packer_config: config.into(),
             skip_metadata_serialization: config.skip_serializing_metadata.unwrap_or_default(),
+            allow_multiple_pages: config.allow_multiple_pages.unwrap_or_default(),
+            trim: config.trim.unwrap_or_default(),
+            trim_alpha_threshold: config.trim_alpha_threshold.unwrap_or_default(),
+            extrusion_fill: config.extrusion_fill.unwrap_or_default(),
+            // libGDX only reads regions rotated counter-clockwise
+            counter_clockwise: config.export_formats.contains(&ExportFormat::LibGdx),
+            packing_algorithm: config.packing_algorithm.unwrap_or_default(),
+            auto_size: config
+                .auto_size
+                .unwrap_or_default()
+                .then(|| AutoSizeConfig {
+                    power_of_two: config.power_of_two.unwrap_or(true),
+                    allow_non_square: config.allow_non_square.unwrap_or_default(),
+                }),
+            sort_heuristic: config.sort_heuristic.unwrap_or_default(),
+            deduplicate: config.deduplicate.unwrap_or_default(),
+            nine_slices: HashMap::new(),
+            pivots: HashMap::new(),
+            detect_animations: config.detect_animations.unwrap_or_default(),
+            animation_frame_duration: config
+                .animation_frame_duration
+                .unwrap_or(animations::DEFAULT_FRAME_DURATION),
+            animations: config.animations.clone(),
         }
     }
 }
 
+/// Sidecar files next to images read during generation.
+#[cfg(all(feature = "config_ext", not(target_arch = "wasm32")))]
+pub const SIDECAR_EXTENSIONS: [&str; 2] = ["slice.json", "pivot.json"];
+
 #[cfg(all(feature = "config_ext", not(target_arch = "wasm32")))]
 impl TilemapGenerationConfig {
     pub fn read_from_file<P>(path: P) -> anyhow::Result<TilemapGenerationConfig>
@@ -260,6 +261,49 @@ impl TilemapGenerationConfig {
         (file_paths, prefix)
     }
 
+    /// Returns nine-slice borders of the image with given `id`.
+    ///
+    /// Reads the `.slice.json` file next to the image at `path` if it exists, otherwise uses the first matching `nine_slices` pattern.
+    pub fn nine_slice_for(
+        &self,
+        id: &str,
+        path: Option<&Path>,
+    ) -> anyhow::Result<Option<AtlasFrameSlice>> {
+        if let Some(slice) = read_sidecar(path, "slice.json")? {
+            return Ok(Some(slice));
+        }
+        for nine_slice in self.nine_slices.iter() {
+            if glob::Pattern::new(&nine_slice.pattern)?.matches(id) {
+                return Ok(Some(nine_slice.border));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the pivot of the image with given `id`.
+    ///
+    /// Reads the `.pivot.json` file next to the image at `path` if it exists, otherwise uses the first matching `pivots` pattern.
+    pub fn pivot_for(&self, id: &str, path: Option<&Path>) -> anyhow::Result<Option<[f32; 2]>> {
+        if let Some(pivot) = read_sidecar(path, "pivot.json")? {
+            return Ok(Some(pivot));
+        }
+        for pivot in self.pivots.iter() {
+            if glob::Pattern::new(&pivot.pattern)?.matches(id) {
+                return Ok(Some(pivot.pivot));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Sets the pivot of the image with given `id`, overriding `pivots` patterns. `None` removes it.
+    pub fn set_pivot(&mut self, id: &str, pivot: Option<[f32; 2]>) {
+        let pattern = glob::Pattern::escape(id);
+        self.pivots.retain(|p| p.pattern != pattern);
+        if let Some(pivot) = pivot {
+            self.pivots.insert(0, PivotConfig { pattern, pivot });
+        }
+    }
+
     pub fn working_dir(&self) -> PathBuf {
         let dir = match &self.working_dir {
             None => std::env::current_dir().expect("msg"),
@@ -275,23 +319,56 @@ impl TilemapGenerationConfig {
         std::path::absolute(dir).unwrap_or_default()
     }
 
+    /// Generates the atlas, skipped when the inputs did not change since the last generation.
+    ///
+    /// Fingerprint of the inputs is stored in a `.rpack_cache` file next to the output.
     pub fn generate(&self) -> anyhow::Result<()> {
+        self.generate_with_cache(true)
+    }
+
+    /// Generates the atlas even if it is up to date.
+    pub fn force_generate(&self) -> anyhow::Result<()> {
+        self.generate_with_cache(false)
+    }
+
+    fn generate_with_cache(&self, use_cache: bool) -> anyhow::Result<()> {
+        use crate::cache::{CACHE_EXTENSION, GenerationCache, InputFingerprint};
         use crate::saving::SaveableImage;
 
         let working_dir = self.working_dir();
 
         let (file_paths, prefix) = self.get_file_paths_and_prefix();
-        let images: Vec<ImageFile> = file_paths
-            .iter()
-            .flat_map(|f| {
-                let id = f
-                    .to_str()
-                    .unwrap_or_default()
-                    .strip_prefix(&prefix)
-                    .unwrap_or_default();
-                ImageFile::at_path(f, id)
-            })
-            .collect();
+        let atlas_config_path = working_dir.join(format!("{}.rpack.json", self.output_path));
+        let cache_path = working_dir.join(format!("{}.{CACHE_EXTENSION}", self.output_path));
+        let inputs = InputFingerprint::new(self, &file_paths)?;
+        if use_cache
+            && GenerationCache::read(&cache_path).is_some_and(|cache| cache.is_up_to_date(&inputs))
+        {
+            println!("Atlas is up to date: {}", atlas_config_path.display());
+            return Ok(());
+        }
+        if cache_path.exists() {
+            std::fs::remove_file(&cache_path)?;
+        }
+        let mut build_config: SpritesheetBuildConfig = self.into();
+        let mut images = Vec::with_capacity(file_paths.len());
+        for path in file_paths.iter() {
+            let id = path
+                .to_str()
+                .unwrap_or_default()
+                .strip_prefix(&prefix)
+                .unwrap_or_default();
+            let Some(image) = ImageFile::at_path(path, id) else {
+                continue;
+            };
+            if let Some(slice) = self.nine_slice_for(&image.id, Some(path))? {
+                build_config.nine_slices.insert(image.id.clone(), slice);
+            }
+            if let Some(pivot) = self.pivot_for(&image.id, Some(path))? {
+                build_config.pivots.insert(image.id.clone(), pivot);
+            }
+            images.push(image);
+        }
         let atlas_image_path = working_dir.join(format!(
             "{}{}",
             self.output_path,
@@ -302,8 +379,25 @@ impl TilemapGenerationConfig {
             .expect("D")
             .to_string_lossy()
             .to_string();
-        let atlas_config_path = working_dir.join(format!("{}.rpack.json", self.output_path));
-        let spritesheet = Spritesheet::build(self, &images, &atlas_filename)?;
+        let spritesheet = Spritesheet::build(build_config, &images, &atlas_filename)?;
+        let atlas_name = Path::new(&self.output_path)
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        // Exporting can fail, so it happens before any file is written
+        let exports = self
+            .export_formats
+            .iter()
+            .map(|format| {
+                Ok((
+                    format,
+                    format.export(&spritesheet.atlas_asset, &atlas_name)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, SpritesheetError>>()?;
+        if let Some(parent) = atlas_image_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
 
         if Path::new(&atlas_config_path).exists() {
             std::fs::remove_file(&atlas_config_path).expect("Could not remove the old file");
@@ -311,9 +405,20 @@ impl TilemapGenerationConfig {
         if Path::new(&atlas_image_path).exists() {
             std::fs::remove_file(&atlas_image_path).expect("Could not remove the old file");
         }
-        spritesheet
-            .image_data
-            .save_with_format_autodetection(&atlas_image_path)?;
+        for page in 0.. {
+            let page_path = atlas_image_path.with_file_name(page_filename(&atlas_filename, page));
+            if !page_path.exists() {
+                break;
+            }
+            std::fs::remove_file(&page_path).expect("Could not remove the old file");
+        }
+        let mut outputs = vec![atlas_config_path.clone()];
+        let pages = spritesheet.atlas_asset.pages();
+        for (page, image) in pages.iter().zip(spritesheet.pages()) {
+            let page_path = atlas_image_path.with_file_name(&page.filename);
+            image.save_with_format_autodetection(&page_path)?;
+            outputs.push(page_path);
+        }
         let json = serde_json::to_string_pretty(&spritesheet.atlas_asset_json)?;
         let mut file = std::fs::File::create(&atlas_config_path)?;
         file.write_all(json.as_bytes())?;
@@ -322,6 +427,60 @@ impl TilemapGenerationConfig {
             spritesheet.atlas_asset.frames.len(),
             atlas_config_path.display()
         );
+        let deduplication = spritesheet.deduplication;
+        if deduplication.aliased_frames > 0 {
+            println!(
+                "Reused {} duplicate images, saved {} pixels ({} bytes)",
+                deduplication.aliased_frames,
+                deduplication.saved_pixels,
+                deduplication.saved_bytes()
+            );
+        }
+        for (format, exported) in exports {
+            for file in exported.iter() {
+                let path = atlas_image_path.with_file_name(&file.filename);
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&path, &file.content)?;
+                outputs.push(path);
+            }
+            if let [file] = exported.as_slice() {
+                let path = atlas_image_path.with_file_name(&file.filename);
+                println!("{} saved at: {}", format, path.display());
+            } else {
+                println!("{} saved {} files", format, exported.len());
+            }
+        }
+        if let Some(rust_module) = &self.rust_module {
+            let path = working_dir.join(rust_module);
+            let code = codegen::rust_module(&spritesheet.atlas_asset);
+            // Unchanged files are not touched to avoid needless recompilation
+            if std::fs::read_to_string(&path).ok().as_deref() != Some(code.as_str()) {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&path, code)?;
+            }
+            println!("Rust module saved at: {}", path.display());
+            outputs.push(path);
+        }
+        if !spritesheet.atlas_asset.animations.is_empty() {
+            println!(
+                "Animations: {}",
+                spritesheet
+                    .atlas_asset
+                    .animations
+                    .keys()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        if self.sort_heuristic == Some(SortHeuristic::Best) {
+            println!("Best sort heuristic: {}", spritesheet.sort_heuristic);
+        }
+        GenerationCache { inputs, outputs }.write(&cache_path)?;
 
         Ok(())
     }
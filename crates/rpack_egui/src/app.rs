@@ -9,11 +9,12 @@ use egui::{
 use egui::{Checkbox, Grid, Vec2};
 use egui_extras::{Column, TableBuilder};
 use once_cell::sync::Lazy;
-use rpack_cli::TilemapGenerationConfig;
+use rpack_cli::{ExtrusionFill, PackingAlgorithm, SortHeuristic, TilemapGenerationConfig};
 use rpack_cli::{
-    ImageFile, Spritesheet, SpritesheetBuildConfig, SpritesheetError, packer::SkylinePacker,
+    ImageFile, Spritesheet, SpritesheetBuildConfig, SpritesheetError,
+    packer::{self, AutoSizeConfig},
 };
-use texture_packer::{Rect, TexturePackerConfig};
+use texture_packer::TexturePackerConfig;
 static INPUT_QUEUE: Lazy<SegQueue<AppImageAction>> = Lazy::new(SegQueue::new);
 pub const MY_ACCENT_COLOR32: Color32 = Color32::from_rgb(230, 102, 1);
 pub const GIT_HASH: &str = env!("GIT_HASH");
@@ -32,6 +33,8 @@ pub enum AppImageAction {
     RebuildAtlas,
     #[cfg(not(target_arch = "wasm32"))]
     ReadFromConfig(TilemapGenerationConfig, PathBuf),
+    #[cfg(not(target_arch = "wasm32"))]
+    SetPivot(String, Option<[f32; 2]>),
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -51,7 +54,7 @@ fn execute<F: Future<Output = ()> + 'static>(f: F) {
 pub enum SpriteSheetState {
     Empty,
     Building,
-    Ok(Spritesheet),
+    Ok(Box<Spritesheet>),
 }
 impl SpriteSheetState {
     pub fn is_ok(&self) -> bool {
@@ -152,47 +155,28 @@ impl Application {
         self.update_min_size();
     }
     pub fn update_min_size(&mut self) {
-        self.data.min_size = self
+        let config: TexturePackerConfig = (&self.data.settings).into();
+        let sizes: Vec<(u32, u32)> = self
             .data
             .image_data
             .iter()
-            .max_by(|a, b| a.width.cmp(&b.width))
-            .map_or(32, |s| s.width)
-            .max(
-                self.data
-                    .image_data
-                    .iter()
-                    .max_by(|a, b| a.height.cmp(&b.height))
-                    .map_or(32, |s| s.height),
-            );
-        let config: TexturePackerConfig = (&self.data.settings).into();
-        for nr in [32, 64, 128, 256, 512, 1024, 2048, 4096] {
-            if nr < self.data.min_size {
-                continue;
-            }
-            let mut packer = SkylinePacker::new(TexturePackerConfig {
-                max_width: nr,
-                max_height: nr,
+            .map(|image| (image.width, image.height))
+            .collect();
+        self.data.min_size = packer::minimal_size(
+            self.data.settings.packing_algorithm.unwrap_or_default(),
+            TexturePackerConfig {
+                max_width: 4096,
+                max_height: 4096,
                 ..config
-            });
-            let mut success = true;
-            for image in &self.data.image_data {
-                let data = Rect {
-                    x: 0,
-                    y: 0,
-                    w: image.width,
-                    h: image.height,
-                };
-                if !packer.can_pack(&data) || packer.pack(&data).is_none() {
-                    success = false;
-                    break;
-                }
-            }
-            if success && nr >= self.data.min_size {
-                self.data.min_size = nr;
-                break;
-            }
-        }
+            },
+            &sizes,
+            AutoSizeConfig {
+                power_of_two: true,
+                allow_non_square: false,
+            },
+        )
+        .map_or(4096, |(size, _)| size)
+        .max(32);
     }
     /// Called once before the first frame.
     #[allow(dead_code, unused_variables, unused_mut)]
@@ -242,12 +226,23 @@ impl Application {
             return;
         }
         self.output = SpriteSheetState::Building;
-        let mut packer_config: TexturePackerConfig = (&self.data.settings).into();
-        if packer_config.max_height < self.data.min_size {
-            packer_config.max_height = self.data.min_size;
+        let mut config: SpritesheetBuildConfig = (&self.data.settings).into();
+        if config.packer_config.max_height < self.data.min_size {
+            config.packer_config.max_height = self.data.min_size;
         }
-        if packer_config.max_width < self.data.min_size {
-            packer_config.max_width = self.data.min_size;
+        if config.packer_config.max_width < self.data.min_size {
+            config.packer_config.max_width = self.data.min_size;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        for file in self.data.image_data.iter() {
+            // Invalid sidecar files are reported by the CLI, the editor skips them
+            let path = Some(std::path::Path::new(&file.path));
+            if let Ok(Some(slice)) = self.data.settings.nine_slice_for(file.id(), path) {
+                config.nine_slices.insert(file.id().to_owned(), slice);
+            }
+            if let Ok(Some(pivot)) = self.data.settings.pivot_for(file.id(), path) {
+                config.pivots.insert(file.id().to_owned(), pivot);
+            }
         }
         let images: Vec<ImageFile> = self
             .data
@@ -255,14 +250,6 @@ impl Application {
             .iter()
             .map(|file| file.data.clone())
             .collect();
-        let config = SpritesheetBuildConfig {
-            packer_config,
-            skip_metadata_serialization: self
-                .data
-                .settings
-                .skip_serializing_metadata
-                .unwrap_or_default(),
-        };
         let path = format!("{}.png", &self.data.settings.output_path);
         execute(async move {
             let result = Spritesheet::build(config, &images, &path);
@@ -419,6 +406,11 @@ impl eframe::App for Application {
                         // Will be called after this loop
                     }
                     #[cfg(not(target_arch = "wasm32"))]
+                    AppImageAction::SetPivot(id, pivot) => {
+                        rebuild = true;
+                        self.data.settings.set_pivot(&id, pivot);
+                    }
+                    #[cfg(not(target_arch = "wasm32"))]
                     AppImageAction::ReadFromConfig(config, path) => {
                         let path_str = path.to_string_lossy().to_string();
                         if self.last_editor_paths.iter().all(|p| path_str != *p) {
@@ -458,7 +450,7 @@ impl eframe::App for Application {
                                 )
                                 .unwrap();
                             ctx.include_bytes("bytes://output.png", out_vec);
-                            self.output = SpriteSheetState::Ok(spritesheet);
+                            self.output = SpriteSheetState::Ok(Box::new(spritesheet));
                         }
                         Err(e) => {
                             self.last_error = Some(e);
@@ -723,6 +715,41 @@ impl eframe::App for Application {
                                         0..=10,
                                     );
                                     ui.end_row();
+                                    changed |= slider_field(
+                                        ui,
+                                        "Texture Extrusion",
+                                        &mut self.data.settings.texture_extrusion,
+                                        0,
+                                        0..=10,
+                                    );
+                                    ui.end_row();
+                                    changed |= extrusion_fill_field(
+                                        ui,
+                                        &mut self.data.settings.extrusion_fill,
+                                    );
+                                    ui.end_row();
+                                    changed |= packing_algorithm_field(
+                                        ui,
+                                        &mut self.data.settings.packing_algorithm,
+                                    );
+                                    ui.end_row();
+                                    changed |= sort_heuristic_field(
+                                        ui,
+                                        &mut self.data.settings.sort_heuristic,
+                                    );
+                                    ui.end_row();
+                                    changed |= checkbox_field(
+                                        ui,
+                                        "Auto Size",
+                                        &mut self.data.settings.auto_size,
+                                    );
+                                    ui.end_row();
+                                    changed |= checkbox_field(
+                                        ui,
+                                        "Allow Rotation",
+                                        &mut self.data.settings.allow_rotation,
+                                    );
+                                    ui.end_row();
                                     let mut skip_metadata = self
                                         .data
                                         .settings
@@ -819,6 +846,10 @@ impl eframe::App for Application {
                                                 {
                                                     INPUT_QUEUE.push(AppImageAction::Remove(index));
                                                 }
+                                                #[cfg(not(target_arch = "wasm32"))]
+                                                ui.menu_button("Pivot", |ui| {
+                                                    pivot_menu(ui, &self.data.settings, file);
+                                                });
                                             },
                                         );
                                     });
@@ -910,10 +941,12 @@ impl eframe::App for Application {
                                 return;
                             };
                             ui.add(Image::from_uri("bytes://output.png").bg_fill(Color32::from_black_alpha(200)).max_size(Vec2::splat(self.view_settings.preview_max_size))).on_hover_text(format!(
-                                "{} sprites\nsize: {}x{}",
+                                "{} sprites\nsize: {}x{}\nsorted by: {}\nduplicates: {}",
                                 data.atlas_asset.frames.len(),
                                 data.atlas_asset.size[0],
-                                data.atlas_asset.size[1]
+                                data.atlas_asset.size[1],
+                                data.sort_heuristic,
+                                data.deduplication.aliased_frames
                             ));
                             ui.separator();
                             ui.add_space(10.0);
@@ -984,3 +1017,157 @@ fn slider_field(
         false
     }
 }
+
+fn checkbox_field(ui: &mut Ui, label: &str, field: &mut Option<bool>) -> bool {
+    ui.label(label);
+    let mut value = field.unwrap_or_default();
+    if ui.checkbox(&mut value, "").changed() {
+        *field = Some(value);
+        true
+    } else {
+        false
+    }
+}
+
+fn extrusion_fill_field(ui: &mut Ui, field: &mut Option<ExtrusionFill>) -> bool {
+    ui.label("Extrusion Fill");
+    let mut value = field.unwrap_or_default();
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        let is_color = matches!(value, ExtrusionFill::Color(_));
+        egui::ComboBox::from_id_salt("extrusion_fill")
+            .selected_text(match value {
+                ExtrusionFill::Transparent => "Transparent",
+                ExtrusionFill::EdgeRepeat => "Edge Repeat",
+                ExtrusionFill::Color(_) => "Color",
+            })
+            .show_ui(ui, |ui| {
+                changed |= ui
+                    .selectable_value(&mut value, ExtrusionFill::Transparent, "Transparent")
+                    .changed();
+                changed |= ui
+                    .selectable_value(&mut value, ExtrusionFill::EdgeRepeat, "Edge Repeat")
+                    .changed();
+                if ui.selectable_label(is_color, "Color").clicked() && !is_color {
+                    value = ExtrusionFill::Color([255, 0, 255, 255]);
+                    changed = true;
+                }
+            });
+        if let ExtrusionFill::Color(color) = &mut value {
+            changed |= ui.color_edit_button_srgba_unmultiplied(color).changed();
+        }
+    });
+    if changed {
+        *field = Some(value);
+    }
+    changed
+}
+
+fn packing_algorithm_field(ui: &mut Ui, field: &mut Option<PackingAlgorithm>) -> bool {
+    ui.label("Packing Algorithm");
+    let mut value = field.unwrap_or_default();
+    let mut changed = false;
+    egui::ComboBox::from_id_salt("packing_algorithm")
+        .selected_text(value.to_string())
+        .show_ui(ui, |ui| {
+            for algorithm in PackingAlgorithm::ALL {
+                changed |= ui
+                    .selectable_value(&mut value, algorithm, algorithm.to_string())
+                    .changed();
+            }
+        });
+    if changed {
+        *field = Some(value);
+    }
+    changed
+}
+
+fn sort_heuristic_field(ui: &mut Ui, field: &mut Option<SortHeuristic>) -> bool {
+    ui.label("Sort Heuristic");
+    let mut value = field.unwrap_or_default();
+    let mut changed = false;
+    egui::ComboBox::from_id_salt("sort_heuristic")
+        .selected_text(value.to_string())
+        .show_ui(ui, |ui| {
+            for heuristic in SortHeuristic::CANDIDATES
+                .into_iter()
+                .chain([SortHeuristic::Best])
+            {
+                changed |= ui
+                    .selectable_value(&mut value, heuristic, heuristic.to_string())
+                    .changed();
+            }
+        });
+    if changed {
+        *field = Some(value);
+    }
+    changed
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+const PIVOT_PRESETS: [(&str, [f32; 2]); 9] = [
+    ("Top Left", [0.0, 0.0]),
+    ("Top Center", [0.5, 0.0]),
+    ("Top Right", [1.0, 0.0]),
+    ("Center Left", [0.0, 0.5]),
+    ("Center", [0.5, 0.5]),
+    ("Center Right", [1.0, 0.5]),
+    ("Bottom Left", [0.0, 1.0]),
+    ("Bottom Center", [0.5, 1.0]),
+    ("Bottom Right", [1.0, 1.0]),
+];
+
+#[cfg(not(target_arch = "wasm32"))]
+fn pivot_menu(ui: &mut Ui, settings: &TilemapGenerationConfig, file: &AppImageData) {
+    let path = std::path::Path::new(&file.path);
+    let current = settings.pivot_for(file.id(), Some(path)).ok().flatten();
+    // Pivot from a sidecar file takes precedence over the config, so it cannot be edited here
+    let sidecar = path.with_extension("pivot.json");
+    let overridden = sidecar.is_file();
+    if overridden {
+        ui.label(format!(
+            "Defined in {}",
+            sidecar.file_name().unwrap_or_default().to_string_lossy()
+        ));
+        ui.separator();
+    }
+    ui.add_enabled_ui(!overridden, |ui| {
+        for (label, pivot) in PIVOT_PRESETS {
+            if ui
+                .add(
+                    Button::new(label)
+                        .frame(false)
+                        .selected(current == Some(pivot)),
+                )
+                .clicked()
+            {
+                INPUT_QUEUE.push(AppImageAction::SetPivot(file.id().to_owned(), Some(pivot)));
+            }
+        }
+        ui.separator();
+        let mut pivot = current.unwrap_or([0.5, 0.5]);
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut pivot[0])
+                        .speed(0.01)
+                        .prefix("x: "),
+                )
+                .changed();
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut pivot[1])
+                        .speed(0.01)
+                        .prefix("y: "),
+                )
+                .changed();
+        });
+        if changed {
+            INPUT_QUEUE.push(AppImageAction::SetPivot(file.id().to_owned(), Some(pivot)));
+        }
+        if ui.add(Button::new("Reset").frame(false)).clicked() {
+            INPUT_QUEUE.push(AppImageAction::SetPivot(file.id().to_owned(), None));
+        }
+    });
+}